@@ -0,0 +1,94 @@
+// Host audio output, the emulator queues interleaved stereo samples into a sink
+
+pub mod wav;
+pub use self::wav::{WavRecorder, WavSink};
+
+#[cfg(all(feature = "audio", not(target_arch = "wasm32")))]
+mod native;
+#[cfg(all(feature = "audio", not(target_arch = "wasm32")))]
+pub use self::native::NativeSink;
+
+use crate::cpu::apu::DEFAULT_SAMPLE_RATE;
+
+// stereo frames the sink should hold, about 60 ms
+const TARGET_LATENCY_MS: usize = 60;
+// most the resampling ratio is moved either way, 0.5% is below audible pitch change
+const MAX_RATE_ADJUST: f64 = 0.005;
+
+pub trait AudioSink {
+    // rate the sink expects samples at
+    fn sample_rate(&self) -> u32;
+
+    // interleaved left and right, -1.0 - 1.0
+    fn queue(&mut self, samples: &[f32]);
+
+    // stereo frames waiting to play, None when the sink isn't played in real time
+    fn buffered_frames(&self) -> Option<usize>;
+}
+
+// Discards everything, for running headless without audio hardware
+pub struct NullSink {
+    sample_rate: u32,
+}
+
+impl NullSink {
+    pub fn new(sample_rate: u32) -> NullSink {
+        NullSink { sample_rate }
+    }
+}
+
+impl Default for NullSink {
+    fn default() -> NullSink {
+        NullSink::new(DEFAULT_SAMPLE_RATE)
+    }
+}
+
+impl AudioSink for NullSink {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn queue(&mut self, _samples: &[f32]) {}
+
+    fn buffered_frames(&self) -> Option<usize> {
+        None
+    }
+}
+
+// The sound device when there is one, otherwise a NullSink
+pub fn default_sink() -> Box<dyn AudioSink> {
+    #[cfg(all(feature = "audio", not(target_arch = "wasm32")))]
+    match NativeSink::new() {
+        Ok(sink) => return Box::new(sink),
+        Err(e) => log::warn!("No audio output: {}", e),
+    }
+    Box::new(NullSink::default())
+}
+
+// Dynamic rate control, the host and emulated clocks never quite agree so the
+// resampling ratio is nudged to hold the sink's buffer around a target fill
+pub struct RateControl {
+    target_frames: usize,
+}
+
+impl RateControl {
+    pub fn new(sample_rate: u32) -> RateControl {
+        RateControl {
+            target_frames: sample_rate as usize * TARGET_LATENCY_MS / 1000,
+        }
+    }
+
+    // Ratio for GameBoy::set_audio_rate_adjust, above 1.0 while the buffer is under target
+    pub fn rate_adjust(&self, buffered_frames: usize) -> f64 {
+        let fill = buffered_frames as f64 / self.target_frames as f64;
+        1.0 + MAX_RATE_ADJUST * (1.0 - fill).clamp(-1.0, 1.0)
+    }
+
+    // The buffer is too full to catch up by resampling, hold off emulating a frame
+    pub fn should_wait(&self, buffered_frames: usize) -> bool {
+        buffered_frames > self.target_frames * 2
+    }
+}
+
+#[cfg(test)]
+mod test_audio;
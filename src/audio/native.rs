@@ -0,0 +1,96 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, SampleFormat, SizedSample, Stream, StreamConfig};
+
+use super::AudioSink;
+
+// drop the oldest samples past this many seconds queued
+const MAX_QUEUED_SECONDS: usize = 1;
+
+// Default output device through cpal, samples go through a queue the device callback drains
+pub struct NativeSink {
+    queue: Arc<Mutex<VecDeque<f32>>>,
+    sample_rate: u32,
+    _stream: Stream, // playback stops when dropped
+}
+
+impl NativeSink {
+    pub fn new() -> Result<NativeSink, String> {
+        let device = cpal::default_host().default_output_device()
+            .ok_or_else(|| String::from("no output device"))?;
+        let supported = device.default_output_config().map_err(|e| e.to_string())?;
+        let sample_format = supported.sample_format();
+        let config = supported.config();
+
+        let queue = Arc::new(Mutex::new(VecDeque::new()));
+        let stream = match sample_format {
+            SampleFormat::F32 => build_stream::<f32>(&device, &config, queue.clone()),
+            SampleFormat::I16 => build_stream::<i16>(&device, &config, queue.clone()),
+            SampleFormat::U16 => build_stream::<u16>(&device, &config, queue.clone()),
+            format => return Err(format!("unsupported sample format {}", format)),
+        }?;
+        stream.play().map_err(|e| e.to_string())?;
+
+        Ok(NativeSink {
+            queue,
+            sample_rate: config.sample_rate.0,
+            _stream: stream,
+        })
+    }
+}
+
+impl AudioSink for NativeSink {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn queue(&mut self, samples: &[f32]) {
+        let mut queue = self.queue.lock().unwrap();
+        queue.extend(samples);
+        let max = self.sample_rate as usize * 2 * MAX_QUEUED_SECONDS;
+        if queue.len() > max {
+            let excess = queue.len() - max;
+            queue.drain(..excess);
+        }
+    }
+
+    fn buffered_frames(&self) -> Option<usize> {
+        Some(self.queue.lock().unwrap().len() / 2)
+    }
+}
+
+// Fill each device frame from the queue, silence when it runs dry
+// mono devices get the average, channels past the second are left silent
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    queue: Arc<Mutex<VecDeque<f32>>>,
+) -> Result<Stream, String>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    let channels = config.channels as usize;
+    device.build_output_stream(
+        config,
+        move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+            let mut queue = queue.lock().unwrap();
+            for frame in data.chunks_mut(channels) {
+                let left = queue.pop_front().unwrap_or(0.0);
+                let right = queue.pop_front().unwrap_or(0.0);
+                for (channel, sample) in frame.iter_mut().enumerate() {
+                    let value = match (channels, channel) {
+                        (1, _) => (left + right) / 2.0,
+                        (_, 0) => left,
+                        (_, 1) => right,
+                        _ => 0.0,
+                    };
+                    *sample = T::from_sample(value);
+                }
+            }
+        },
+        |e| log::error!("Audio stream error: {}", e),
+        None,
+    ).map_err(|e| e.to_string())
+}
@@ -0,0 +1,31 @@
+use super::*;
+
+#[test]
+fn test_null_sink() {
+    let mut sink = NullSink::new(48000);
+    sink.queue(&[0.5; 1600]);
+    assert_eq!(sink.sample_rate(), 48000);
+    assert_eq!(sink.buffered_frames(), None);
+    assert_eq!(NullSink::default().sample_rate(), DEFAULT_SAMPLE_RATE);
+}
+
+#[test]
+fn test_rate_adjust() {
+    // 2880 frames is the 60 ms target at 48 kHz
+    let control = RateControl::new(48000);
+    assert_eq!(control.rate_adjust(2880), 1.0);
+    // an empty buffer asks for the most extra samples, a full one for the fewest
+    assert_eq!(control.rate_adjust(0), 1.0 + MAX_RATE_ADJUST);
+    assert_eq!(control.rate_adjust(5760), 1.0 - MAX_RATE_ADJUST);
+    assert_eq!(control.rate_adjust(100000), 1.0 - MAX_RATE_ADJUST);
+    assert!(control.rate_adjust(2000) > 1.0);
+    assert!(control.rate_adjust(3000) < 1.0);
+}
+
+#[test]
+fn test_should_wait() {
+    let control = RateControl::new(48000);
+    assert!(!control.should_wait(0));
+    assert!(!control.should_wait(5760));
+    assert!(control.should_wait(5761));
+}
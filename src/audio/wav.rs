@@ -0,0 +1,114 @@
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+
+use super::AudioSink;
+
+fn to_io_error(e: hound::Error) -> io::Error {
+    match e {
+        hound::Error::IoError(e) => e,
+        e => io::Error::other(e),
+    }
+}
+
+// Writes 16 bit stereo PCM to a WAV file, the header is completed by finalize
+pub struct WavSink {
+    writer: hound::WavWriter<BufWriter<File>>,
+    sample_rate: u32,
+}
+
+impl WavSink {
+    pub fn create<P: AsRef<Path>>(path: P, sample_rate: u32) -> io::Result<WavSink> {
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let writer = hound::WavWriter::create(path, spec).map_err(to_io_error)?;
+        Ok(WavSink { writer, sample_rate })
+    }
+
+    pub fn write(&mut self, samples: &[f32]) -> io::Result<()> {
+        for sample in samples {
+            let sample = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+            self.writer.write_sample(sample).map_err(to_io_error)?;
+        }
+        Ok(())
+    }
+
+    // Write the final data length into the header, dropping does the same but ignores errors
+    pub fn finalize(self) -> io::Result<()> {
+        self.writer.finalize().map_err(to_io_error)
+    }
+}
+
+impl AudioSink for WavSink {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn queue(&mut self, samples: &[f32]) {
+        if let Err(e) = self.write(samples) {
+            log::error!("Couldn't write WAV samples: {}", e);
+        }
+    }
+
+    fn buffered_frames(&self) -> Option<usize> {
+        None
+    }
+}
+
+// Records the mix, and optionally each channel to its own file next to it
+// music.wav gets music.ch1.wav - music.ch4.wav, use GameBoy::set_recording and
+// set_channel_capture for samples that don't depend on the host's rate adjustment
+pub struct WavRecorder {
+    mix: WavSink,
+    channels: Option<[WavSink; 4]>,
+}
+
+impl WavRecorder {
+    pub fn create<P: AsRef<Path>>(path: P, sample_rate: u32, per_channel: bool) -> io::Result<WavRecorder> {
+        let path = path.as_ref();
+        let mix = WavSink::create(path, sample_rate)?;
+        let channels = if per_channel {
+            Some([
+                WavSink::create(channel_path(path, 1), sample_rate)?,
+                WavSink::create(channel_path(path, 2), sample_rate)?,
+                WavSink::create(channel_path(path, 3), sample_rate)?,
+                WavSink::create(channel_path(path, 4), sample_rate)?,
+            ])
+        } else {
+            None
+        };
+        Ok(WavRecorder { mix, channels })
+    }
+
+    // mix from GameBoy::recorded_samples, channels from GameBoy::channel_audio_samples
+    pub fn write(&mut self, mix: &[f32], channels: &[Vec<f32>; 4]) -> io::Result<()> {
+        self.mix.write(mix)?;
+        if let Some(sinks) = self.channels.as_mut() {
+            for (sink, samples) in sinks.iter_mut().zip(channels.iter()) {
+                sink.write(samples)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn finalize(self) -> io::Result<()> {
+        self.mix.finalize()?;
+        for sink in self.channels.into_iter().flatten() {
+            sink.finalize()?;
+        }
+        Ok(())
+    }
+}
+
+// music.wav -> music.ch1.wav
+pub fn channel_path(path: &Path, channel: u8) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{}.ch{}.wav", stem, channel))
+}
+
+#[cfg(test)]
+mod test_wav;
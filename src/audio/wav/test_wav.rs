@@ -0,0 +1,117 @@
+use super::*;
+use crate::cpu::cartridge::rom_with_program;
+use crate::GameBoy;
+
+// Deletes its files when dropped, so a failed assertion doesn't leave them behind
+struct TempFiles(Vec<PathBuf>);
+
+impl TempFiles {
+    // a WAV path in the temp directory along with its per channel files
+    fn wav(name: &str) -> TempFiles {
+        let path = std::env::temp_dir().join(format!("rusty-gb-{}-{}.wav", name, std::process::id()));
+        let mut files = vec![path.clone()];
+        files.extend((1..=4).map(|channel| channel_path(&path, channel)));
+        TempFiles(files)
+    }
+
+    fn path(&self) -> &Path {
+        &self.0[0]
+    }
+}
+
+impl Drop for TempFiles {
+    fn drop(&mut self) {
+        for file in &self.0 {
+            let _ = std::fs::remove_file(file);
+        }
+    }
+}
+
+#[test]
+fn test_channel_path() {
+    assert_eq!(channel_path(Path::new("out/music.wav"), 3), PathBuf::from("out/music.ch3.wav"));
+    assert_eq!(channel_path(Path::new("music"), 1), PathBuf::from("music.ch1.wav"));
+}
+
+#[test]
+fn test_wav_sink() {
+    let files = TempFiles::wav("sink");
+    let path = files.path();
+    let mut sink = WavSink::create(path, 48000).unwrap();
+    assert_eq!(sink.buffered_frames(), None);
+    sink.queue(&[0.0, 1.0, -1.0, 0.5, 2.0, -2.0]);
+    sink.finalize().unwrap();
+
+    let mut reader = hound::WavReader::open(path).unwrap();
+    let spec = reader.spec();
+    assert_eq!(spec.channels, 2);
+    assert_eq!(spec.sample_rate, 48000);
+    assert_eq!(spec.bits_per_sample, 16);
+    let samples: Vec<i16> = reader.samples().map(Result::unwrap).collect();
+    // out of range samples are clipped
+    assert_eq!(samples, vec![0, 32767, -32767, 16383, 32767, -32767]);
+}
+
+#[test]
+fn test_wav_recorder() {
+    let files = TempFiles::wav("recorder");
+    let path = files.path();
+    let mut recorder = WavRecorder::create(path, 44100, true).unwrap();
+    let channels = [vec![0.5; 4], vec![0.0; 4], vec![-0.5; 4], vec![0.25; 4]];
+    recorder.write(&[0.1; 4], &channels).unwrap();
+    recorder.finalize().unwrap();
+
+    let read = |path: &Path| -> Vec<i16> {
+        hound::WavReader::open(path).unwrap().samples().map(Result::unwrap).collect()
+    };
+    assert_eq!(read(path), vec![3276; 4]);
+    for (channel, expected) in [(1, 16383), (2, 0), (3, -16383), (4, 8191)] {
+        assert_eq!(read(&channel_path(path, channel)), vec![expected; 4]);
+    }
+
+    // just the mix without per channel files
+    let files = TempFiles::wav("recorder-mix");
+    let path = files.path();
+    let recorder = WavRecorder::create(path, 44100, false).unwrap();
+    recorder.finalize().unwrap();
+    assert!(path.exists());
+    assert!(!channel_path(path, 1).exists());
+}
+
+// plays channel 2 then loops
+fn tone_rom() -> Vec<u8> {
+    // LD A, 0xF0; LDH (NR22), A; LD A, 0x87; LDH (NR24), A; JR -2
+    rom_with_program(&[0x3E, 0xF0, 0xE0, 0x17, 0x3E, 0x87, 0xE0, 0x19, 0x18, 0xFE])
+}
+
+#[test]
+fn test_recording_ignores_rate_adjust() {
+    let mut captures = Vec::new();
+    let mut played = Vec::new();
+    for (run, adjust) in [0.995, 1.005].into_iter().enumerate() {
+        let files = TempFiles::wav(&format!("adjust-{}", run));
+        let path = files.path();
+        let mut gameboy = GameBoy::new();
+        gameboy.set_recording(true);
+        gameboy.set_channel_capture(true);
+        gameboy.load_rom(&tone_rom()).unwrap();
+        gameboy.set_audio_rate_adjust(adjust);
+
+        let mut recorder = WavRecorder::create(path, gameboy.sample_rate(), true).unwrap();
+        let mut played_samples = 0;
+        for _ in 0..10 {
+            gameboy.run_frame();
+            played_samples += gameboy.audio_samples().len();
+            recorder.write(&gameboy.recorded_samples(), &gameboy.channel_audio_samples()).unwrap();
+        }
+        recorder.finalize().unwrap();
+        played.push(played_samples);
+
+        captures.push(files.0.iter().map(|file| std::fs::read(file).unwrap()).collect::<Vec<_>>());
+    }
+
+    // playback followed the adjustment, the recordings didn't
+    assert!(played[0] < played[1]);
+    assert_eq!(captures[0], captures[1]);
+    assert!(captures[0][0].len() > 44);
+}
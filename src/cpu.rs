@@ -10,6 +10,7 @@ pub use self::instructions::ArithmeticTarget16;
 pub use self::instructions::ControlCondition;
 pub use self::instructions::RstValue;
 pub use self::instructions::JumpAddr;
+pub use self::instructions::PrefixTarget;
 
 pub mod instruction_cycle_table;
 pub use self::instruction_cycle_table::get_cycle_count;
@@ -18,43 +19,48 @@ pub mod cpu_registers;
 pub use self::cpu_registers::Registers;
 pub use self::cpu_registers::FlagsRegister;
 
+pub mod interrupts;
+pub use self::interrupts::Interrupt;
+pub use self::interrupts::InterruptController;
+
+pub mod timer;
+pub use self::timer::Timer;
+
 pub mod ppu;
 pub use self::ppu::PPU;
 
-use winit::event_loop::{EventLoop};
+pub mod apu;
+pub use self::apu::APU;
 
-pub struct MemoryBus {
-    pub memory: [u8; 0xFFFF],
-}
+pub mod cartridge;
+pub use self::cartridge::Cartridge;
 
-impl MemoryBus {
-    fn read_byte(&self, address: u16) -> u8 {
-        self.memory[address as usize]
-    }
-    fn write_byte(&mut self, address: u16, value: u8) {
-        self.memory[address as usize] = value;
-    }
-}
+pub mod joypad;
+pub use self::joypad::Joypad;
+
+pub mod serial;
+pub use self::serial::Serial;
+
+pub mod memory_bus;
+pub use self::memory_bus::MemoryBus;
 
 pub struct CPU {
-    pub frequency: u64, // Hz
-    pub frame_delay: u64, // microseconds aka (1 / fps) * 1000 * 1000
     pub reg: Registers,
     pub bus: MemoryBus,
     pub pc: u16,
     pub sp: u16,
-    pub interrupt_enable: bool,
+    pub ime: bool, // interrupt master enable
+    pub ime_scheduled: bool, // EI takes effect after the next instruction
+    pub halt_bug: bool, // next opcode is read without incrementing pc
     pub is_halted: bool,
     pub is_stopped: bool,
-    pub ppu: Option<PPU>,
+    pub is_locked: bool, // set by illegal opcodes, only a reset recovers
 }
 
 impl CPU {
-    pub fn new(event_loop: &EventLoop<()>) -> CPU {
+    pub fn new() -> CPU {
         // create a CPU with default values
         CPU {
-            frequency: 4194304, // 4.194304 MHz
-            frame_delay: 16750, // equivalent to 59.7 fps
             reg: Registers {
                 a: 0,
                 b: 0,
@@ -70,50 +76,43 @@ impl CPU {
                 h: 0,
                 l: 0,
             },
-            bus: MemoryBus {
-                memory: [0; 0xFFFF],
-            },
+            bus: MemoryBus::new(),
             pc: 0,
             sp: 0xFFFF,
-            interrupt_enable: true,
+            ime: false,
+            ime_scheduled: false,
+            halt_bug: false,
             is_halted: true,
             is_stopped: true,
-            ppu: Some(PPU::new(&event_loop))
+            is_locked: false,
         }
     }
 
-    pub fn new_test() -> CPU {
-        // create a CPU without a PPU
-        CPU {
-            frequency: 4194304, // 4.194304 MHz
-            frame_delay: 16750, // equivalent to 59.7 fps
-            reg: Registers {
-                a: 0,
-                b: 0,
-                c: 0,
-                d: 0,
-                e: 0,
-                f: FlagsRegister{
-                    zero:       false,
-                    subtract:   false,
-                    half_carry: false,
-                    carry:      false,
-                },
-                h: 0,
-                l: 0,
-            },
-            bus: MemoryBus {
-                memory: [0; 0xFFFF],
-            },
-            pc: 0,
-            sp: 0xFFFF,
-            interrupt_enable: true,
-            is_halted: true,
-            is_stopped: true,
-            ppu: None
-        }
+    // Start from 0x0000 with the boot ROM mapped over the cartridge
+    pub fn start_boot_rom(&mut self, boot_rom: [u8; 0x100]) {
+        self.bus.boot_rom = Some(boot_rom);
+        self.pc = 0;
+        self.is_halted = false;
+        self.is_stopped = false;
     }
 
+    // Skip the boot ROM, registers and IO are set to what the DMG boot ROM leaves behind
+    pub fn start_post_boot(&mut self) {
+        self.reg.set_af(0x01B0);
+        self.reg.set_bc(0x0013);
+        self.reg.set_de(0x00D8);
+        self.reg.set_hl(0x014D);
+        // half carry and carry are only set when the header checksum is non-zero
+        if self.bus.cartridge.header.header_checksum == 0 {
+            self.reg.f.half_carry = false;
+            self.reg.f.carry = false;
+        }
+        self.sp = 0xFFFE;
+        self.pc = 0x100;
+        self.bus.init_post_boot();
+        self.is_halted = false;
+        self.is_stopped = false;
+    }
 
     // Executes given instruction and returns (next pc, extra_cycles)
     fn execute(&mut self, instruction: Instruction) -> (u16, u8) {
@@ -345,8 +344,7 @@ impl CPU {
                         self.reg.set_hl(value);
                     }
                     ArithmeticTarget16::SPIMM  => {
-                        let imm: i8 = self.bus.read_byte(self.pc + 1) as i8;
-                        self.sp = self.sp.wrapping_add(imm as u16);
+                        self.sp = self.add_sp_imm(self.bus.read_byte(self.pc + 1));
                     }
                 };
                 // return next PC value
@@ -375,16 +373,17 @@ impl CPU {
                             LoadWordSource::DE  => self.reg.get_de(),
                             LoadWordSource::HL  => self.reg.get_hl(),
                             LoadWordSource::SP  => self.sp,
-                            LoadWordSource::POP => ((self.pop() as u16) << 8) | (self.pop() as u16),
+                            LoadWordSource::POP => {
+                                // low byte is on top of the stack
+                                let low = self.pop();
+                                let high = self.pop();
+                                ((high as u16) << 8) | (low as u16)
+                            },
                             LoadWordSource::AF => self.reg.get_af(),
                             LoadWordSource::IMM16 => {
                                 self.bus.read_byte(self.pc + 1) as u16 | ((self.bus.read_byte(self.pc + 2) as u16) << 8)
                             },
-                            LoadWordSource::SPIMM => {
-                                let value = self.add16(self.sp, self.bus.read_byte(self.pc + 1) as u16);
-                                self.reg.f.zero = false;
-                                value
-                            }
+                            LoadWordSource::SPIMM => self.add_sp_imm(self.bus.read_byte(self.pc + 1)),
                         };
 
                         match dest {
@@ -506,8 +505,10 @@ impl CPU {
                     _ => panic!("Unsupported CALL ControlCondition"),
                 }
                 {
-                    // calling takes 12 extra cycles
-                    extra_cycles = 12;
+                    // taking a conditional call takes 12 extra cycles
+                    if !matches!(condition, ControlCondition::NONE) {
+                        extra_cycles = 12;
+                    }
 
                     // save pc and return new pc
                     let return_pc = self.pc + 3;
@@ -528,18 +529,20 @@ impl CPU {
                     ControlCondition::C       => self.reg.f.carry,
                     ControlCondition::NONE    => true,
                     ControlCondition::NONEEI => {
-                        // TODO: unsure that interrupts should be enabled this early during RETI
-                        self.interrupt_enable = true;
+                        // unlike EI, RETI enables interrupts without delay
+                        self.ime = true;
                         true
                     }
                 }
                 {
-                    // returning takes 12 extra cycles
-                    extra_cycles = 12;
+                    // taking a conditional return takes 12 extra cycles
+                    if !matches!(condition, ControlCondition::NONE | ControlCondition::NONEEI) {
+                        extra_cycles = 12;
+                    }
 
-                    // return to the pc on stack
-                    let pch = self.pop();
+                    // return to the pc on stack, low byte is on top
                     let pcl = self.pop();
+                    let pch = self.pop();
                     ((pch as u16) << 8) | (pcl as u16)
                 }
                 else {
@@ -558,8 +561,10 @@ impl CPU {
                     RstValue::H28 => 0x28,
                     RstValue::H38 => 0x38,
                 };
-                self.push((self.pc >> 8) as u8);
-                self.push((self.pc & 0xFF) as u8);
+                // save address of the next instruction
+                let return_pc = self.pc.wrapping_add(1);
+                self.push((return_pc >> 8) as u8);
+                self.push((return_pc & 0xFF) as u8);
                 value as u16
             }
 
@@ -573,14 +578,20 @@ impl CPU {
                     _ => panic!("Unsupported JP ControlCondition"),
                 }
                 {
-                    // taking jump takes 4 extra cycles
-                    extra_cycles = 4;
+                    // taking a conditional jump takes 4 extra cycles
+                    if !matches!(condition, ControlCondition::NONE) {
+                        extra_cycles = 4;
+                    }
 
                     // return next pc
                     match addr_type {
                         JumpAddr::IMM16 => (self.bus.read_byte(self.pc + 1) as u16) | ((self.bus.read_byte(self.pc + 2) as u16) << 8),
                         JumpAddr::HL    => self.reg.get_hl(),
-                        JumpAddr::REL   => self.pc.wrapping_add(self.bus.read_byte(self.pc + 1) as u16),
+                        // signed offset is relative to the end of the instruction
+                        JumpAddr::REL   => {
+                            let offset = self.bus.read_byte(self.pc + 1) as i8;
+                            self.pc.wrapping_add(2).wrapping_add(offset as u16)
+                        }
                     }
                 }
                 else {
@@ -598,22 +609,31 @@ impl CPU {
             }
 
             Instruction::STOP => {
+                // STOP resets the divider
+                self.bus.timer.write_div();
                 self.is_stopped = true;
                 self.pc + 1
             }
 
             Instruction::HALT => {
-                self.is_halted = true;
+                if !self.ime && self.bus.interrupts.pending() != 0 {
+                    // HALT bug: CPU doesn't halt and fails to increment pc
+                    // after reading the next opcode
+                    self.halt_bug = true;
+                } else {
+                    self.is_halted = true;
+                }
                 self.pc + 1
             }
 
             Instruction::DI => {
-                self.interrupt_enable = false;
+                self.ime = false;
+                self.ime_scheduled = false;
                 self.pc + 1
             }
 
             Instruction::EI => {
-                self.interrupt_enable = true;
+                self.ime_scheduled = true;
                 self.pc + 1
             }
 
@@ -664,38 +684,222 @@ impl CPU {
                 self.reg.f.carry = true;
                 self.pc + 1
             }
+
+            // rotates on A always clear the zero flag: 0 0 0 C
+
+            Instruction::RLCA => {
+                self.reg.a = self.rlc(self.reg.a);
+                self.reg.f.zero = false;
+                self.pc + 1
+            }
+
+            Instruction::RRCA => {
+                self.reg.a = self.rrc(self.reg.a);
+                self.reg.f.zero = false;
+                self.pc + 1
+            }
+
+            Instruction::RLA => {
+                self.reg.a = self.rl(self.reg.a);
+                self.reg.f.zero = false;
+                self.pc + 1
+            }
+
+            Instruction::RRA => {
+                self.reg.a = self.rr(self.reg.a);
+                self.reg.f.zero = false;
+                self.pc + 1
+            }
+
+            // 0xCB prefixed instructions are all two bytes long
+
+            Instruction::RLC(target) => {
+                let value = self.rlc(self.read_prefix_target(target));
+                self.write_prefix_target(target, value);
+                self.pc.wrapping_add(2)
+            }
+
+            Instruction::RRC(target) => {
+                let value = self.rrc(self.read_prefix_target(target));
+                self.write_prefix_target(target, value);
+                self.pc.wrapping_add(2)
+            }
+
+            Instruction::RL(target) => {
+                let value = self.rl(self.read_prefix_target(target));
+                self.write_prefix_target(target, value);
+                self.pc.wrapping_add(2)
+            }
+
+            Instruction::RR(target) => {
+                let value = self.rr(self.read_prefix_target(target));
+                self.write_prefix_target(target, value);
+                self.pc.wrapping_add(2)
+            }
+
+            Instruction::SLA(target) => {
+                let value = self.sla(self.read_prefix_target(target));
+                self.write_prefix_target(target, value);
+                self.pc.wrapping_add(2)
+            }
+
+            Instruction::SRA(target) => {
+                let value = self.sra(self.read_prefix_target(target));
+                self.write_prefix_target(target, value);
+                self.pc.wrapping_add(2)
+            }
+
+            Instruction::SWAP(target) => {
+                let value = self.swap(self.read_prefix_target(target));
+                self.write_prefix_target(target, value);
+                self.pc.wrapping_add(2)
+            }
+
+            Instruction::SRL(target) => {
+                let value = self.srl(self.read_prefix_target(target));
+                self.write_prefix_target(target, value);
+                self.pc.wrapping_add(2)
+            }
+
+            Instruction::BIT(bit, target) => {
+                // Test bit and update flags: Z 0 1 -
+                let value = self.read_prefix_target(target);
+                self.reg.f.zero = value & (1 << bit as u8) == 0;
+                self.reg.f.subtract = false;
+                self.reg.f.half_carry = true;
+                self.pc.wrapping_add(2)
+            }
+
+            Instruction::RES(bit, target) => {
+                let value = self.read_prefix_target(target) & !(1 << bit as u8);
+                self.write_prefix_target(target, value);
+                self.pc.wrapping_add(2)
+            }
+
+            Instruction::SET(bit, target) => {
+                let value = self.read_prefix_target(target) | (1 << bit as u8);
+                self.write_prefix_target(target, value);
+                self.pc.wrapping_add(2)
+            }
         };
 
         (next_pc, extra_cycles)
     }
 
-    // Reads and executes instruction at pc
+    // Services a pending interrupt and returns the cycles it took
+    fn handle_interrupts(&mut self) -> u8 {
+        if self.bus.interrupts.pending() == 0 {
+            return 0;
+        }
+
+        // any pending interrupt wakes the CPU, even with IME off
+        self.is_halted = false;
+
+        if !self.ime {
+            return 0;
+        }
+
+        let interrupt = match self.bus.interrupts.highest_pending() {
+            Some(interrupt) => interrupt,
+            None => return 0,
+        };
+
+        self.ime = false;
+        self.ime_scheduled = false;
+        self.bus.interrupts.acknowledge(interrupt);
+        // EI then HALT with an interrupt pending returns to the HALT instead of rereading a byte
+        if self.halt_bug {
+            self.halt_bug = false;
+            self.pc = self.pc.wrapping_sub(1);
+        }
+        self.push((self.pc >> 8) as u8);
+        self.push((self.pc & 0xFF) as u8);
+        self.pc = interrupt.vector();
+        20
+    }
+
+    // Runs one instruction (or interrupt dispatch) and clocks the rest of
+    // the system by the cycles it took
     pub fn step(&mut self) -> u8 {
+        let cycles = self.step_instruction();
+        self.bus.tick(cycles);
+        cycles
+    }
+
+    // Reads and executes instruction at pc
+    fn step_instruction(&mut self) -> u8 {
+        // a locked CPU never fetches again, but the clock keeps running
+        if self.is_locked {
+            return 4;
+        }
+
+        // STOP lasts until a joypad line goes low
+        if self.is_stopped {
+            let joypad_requested = self.bus.interrupts.flags & Interrupt::Joypad.mask() != 0;
+            if !self.bus.joypad.any_line_low() && !joypad_requested {
+                return 4;
+            }
+            // the divider doesn't run while stopped
+            self.is_stopped = false;
+            self.bus.timer.write_div();
+        }
+
+        let interrupt_cycles = self.handle_interrupts();
+        if interrupt_cycles > 0 {
+            return interrupt_cycles;
+        }
+
+        if self.is_halted {
+            return 4;
+        }
+
+        // EI enables interrupts once the instruction following it is done
+        let enable_ime = self.ime_scheduled;
+
         let mut instruction_byte = self.bus.read_byte(self.pc);
         let prefixed = instruction_byte == 0xCB;
 
         if prefixed {
-            instruction_byte = self.bus.read_byte(self.pc + 1);
+            instruction_byte = self.bus.read_byte(self.pc.wrapping_add(1));
+        }
+
+        if self.halt_bug {
+            // the opcode byte is read again as the first operand
+            self.halt_bug = false;
+            self.pc = self.pc.wrapping_sub(1);
         }
 
         let (next_pc, extra_cycles) = if let Some(instruction) = Instruction::from_byte(instruction_byte, prefixed) {
             self.execute(instruction)
         } else {
-            let description = format!("0x{}{:x}", if prefixed { "CB" } else { "" }, instruction_byte);
-            panic!("Unknown instruction {}", description);
+            // only unprefixed opcodes can be illegal, hardware freezes on them
+            log::error!("Illegal instruction 0x{:02X}, CPU locked at PC={:04X}", instruction_byte, self.pc);
+            self.is_locked = true;
+            return 4;
         };
 
         self.pc = next_pc;
+
+        // DI in between cancels the scheduled enable
+        if enable_ime && self.ime_scheduled {
+            self.ime = true;
+            self.ime_scheduled = false;
+        }
+
         extra_cycles + get_cycle_count(instruction_byte, prefixed)
     }
 
-    // Execute however many instructions fit in a frame
+    // Run until the PPU completes a frame, or for a frame's worth of cycles while the LCD is off
     pub fn frame_step(&mut self) {
-        let cycles_per_frame = (self.frequency * self.frame_delay ) / 1000 / 1000;
         let mut current_frame_cycles = 0;
 
-        while current_frame_cycles < cycles_per_frame {
+        while current_frame_cycles < ppu::DOTS_PER_FRAME {
             current_frame_cycles += self.step() as u64;
+
+            if self.bus.ppu.frame_complete {
+                self.bus.ppu.frame_complete = false;
+                return;
+            }
         }
     }
 
@@ -719,6 +923,16 @@ impl CPU {
         new_value
     }
 
+    // Return SP + signed value and update flags: 0 0 H C
+    // flags are computed from the unsigned addition on the low byte
+    fn add_sp_imm(&mut self, value: u8) -> u16 {
+        self.reg.f.zero = false;
+        self.reg.f.subtract = false;
+        self.reg.f.half_carry = (self.sp & 0xF) + (value as u16 & 0xF) > 0xF;
+        self.reg.f.carry = (self.sp & 0xFF) + (value as u16) > 0xFF;
+        self.sp.wrapping_add(value as i8 as u16)
+    }
+
     // Return A + value + carry and update flags: Z 0 H C
     fn adc(&mut self, value: u8) -> u8 {
         let (first, did_overflow_first) = self.reg.a.overflowing_add(value);
@@ -799,6 +1013,95 @@ impl CPU {
         new_value
     }
 
+    // Return value rotated left, bit 7 into carry, update flags: Z 0 0 C
+    fn rlc(&mut self, value: u8) -> u8 {
+        let new_value = value.rotate_left(1);
+        self.set_shift_flags(new_value, value & 0x80 != 0);
+        new_value
+    }
+
+    // Return value rotated right, bit 0 into carry, update flags: Z 0 0 C
+    fn rrc(&mut self, value: u8) -> u8 {
+        let new_value = value.rotate_right(1);
+        self.set_shift_flags(new_value, value & 0x01 != 0);
+        new_value
+    }
+
+    // Return value rotated left through carry and update flags: Z 0 0 C
+    fn rl(&mut self, value: u8) -> u8 {
+        let new_value = (value << 1) | self.reg.f.carry as u8;
+        self.set_shift_flags(new_value, value & 0x80 != 0);
+        new_value
+    }
+
+    // Return value rotated right through carry and update flags: Z 0 0 C
+    fn rr(&mut self, value: u8) -> u8 {
+        let new_value = (value >> 1) | ((self.reg.f.carry as u8) << 7);
+        self.set_shift_flags(new_value, value & 0x01 != 0);
+        new_value
+    }
+
+    // Return value shifted left, bit 0 cleared, update flags: Z 0 0 C
+    fn sla(&mut self, value: u8) -> u8 {
+        let new_value = value << 1;
+        self.set_shift_flags(new_value, value & 0x80 != 0);
+        new_value
+    }
+
+    // Return value shifted right, bit 7 kept, update flags: Z 0 0 C
+    fn sra(&mut self, value: u8) -> u8 {
+        let new_value = (value >> 1) | (value & 0x80);
+        self.set_shift_flags(new_value, value & 0x01 != 0);
+        new_value
+    }
+
+    // Return value with nibbles swapped and update flags: Z 0 0 0
+    fn swap(&mut self, value: u8) -> u8 {
+        let new_value = value.rotate_left(4);
+        self.set_shift_flags(new_value, false);
+        new_value
+    }
+
+    // Return value shifted right, bit 7 cleared, update flags: Z 0 0 C
+    fn srl(&mut self, value: u8) -> u8 {
+        let new_value = value >> 1;
+        self.set_shift_flags(new_value, value & 0x01 != 0);
+        new_value
+    }
+
+    fn set_shift_flags(&mut self, value: u8, carry: bool) {
+        self.reg.f.zero = value == 0;
+        self.reg.f.subtract = false;
+        self.reg.f.half_carry = false;
+        self.reg.f.carry = carry;
+    }
+
+    fn read_prefix_target(&self, target: PrefixTarget) -> u8 {
+        match target {
+            PrefixTarget::B  => self.reg.b,
+            PrefixTarget::C  => self.reg.c,
+            PrefixTarget::D  => self.reg.d,
+            PrefixTarget::E  => self.reg.e,
+            PrefixTarget::H  => self.reg.h,
+            PrefixTarget::L  => self.reg.l,
+            PrefixTarget::HL => self.bus.read_byte(self.reg.get_hl()),
+            PrefixTarget::A  => self.reg.a,
+        }
+    }
+
+    fn write_prefix_target(&mut self, target: PrefixTarget, value: u8) {
+        match target {
+            PrefixTarget::B  => self.reg.b = value,
+            PrefixTarget::C  => self.reg.c = value,
+            PrefixTarget::D  => self.reg.d = value,
+            PrefixTarget::E  => self.reg.e = value,
+            PrefixTarget::H  => self.reg.h = value,
+            PrefixTarget::L  => self.reg.l = value,
+            PrefixTarget::HL => self.bus.write_byte(self.reg.get_hl(), value),
+            PrefixTarget::A  => self.reg.a = value,
+        }
+    }
+
     fn push(&mut self, value: u8) {
         self.bus.write_byte(self.sp - 1, value);
         self.sp -= 1;
@@ -0,0 +1,270 @@
+pub mod length_counter;
+pub mod envelope;
+pub mod sweep;
+
+pub mod pulse;
+pub use self::pulse::PulseChannel;
+
+pub mod wave;
+pub use self::wave::WaveChannel;
+
+pub mod noise;
+pub use self::noise::NoiseChannel;
+
+pub mod mixer;
+use self::mixer::StereoOutput;
+
+pub mod blip_buffer;
+
+// CPU clock, the APU runs one step per cycle
+const CLOCK_RATE: u32 = 4194304;
+// channels are mixed once per machine cycle, about 1 MHz
+const MIX_DIVIDER: u32 = 4;
+pub const DEFAULT_SAMPLE_RATE: u32 = 44100;
+
+// frame sequencer steps on the falling edge of DIV bit 4, 512 Hz
+const FRAME_SEQUENCER_DIV_BIT: u8 = 4;
+
+const POWER_BYTE_POSITION: u8 = 7;
+
+// Audio processing unit
+// 0xFF10 - 0xFF14 channel 1, pulse with sweep
+// 0xFF16 - 0xFF19 channel 2, pulse
+// 0xFF1A - 0xFF1E channel 3, wave
+// 0xFF20 - 0xFF23 channel 4, noise
+// 0xFF24          NR50, master volume
+// 0xFF25          NR51, channel panning
+// 0xFF26          NR52, power and channel status
+// 0xFF30 - 0xFF3F wave RAM
+pub struct APU {
+    pub power: bool,
+    pub channel1: PulseChannel,
+    pub channel2: PulseChannel,
+    pub channel3: WaveChannel,
+    pub channel4: NoiseChannel,
+    pub nr50: u8,
+    pub nr51: u8,
+    frame_sequencer_step: u8,
+    last_div_bit: bool,
+    sample_rate: u32,
+    rate_adjust: f64, // scales the resampling ratio to keep a host audio buffer from draining or filling
+    mix_clock: u32,
+    output: StereoOutput,
+    // recordings run at exactly the sample rate so captures match from run to run
+    record_output: Option<StereoOutput>, // the mix, only while recording
+    channel_outputs: Option<Box<[StereoOutput; 4]>>, // each channel on its own, only while capturing
+    pub samples: Vec<f32>, // interleaved left and right, -1.0 - 1.0
+    pub record_samples: Vec<f32>, // like samples, without the rate adjustment
+    pub channel_samples: [Vec<f32>; 4], // like record_samples, for channels 1 - 4 alone
+}
+
+impl APU {
+    pub fn new() -> APU {
+        APU {
+            power: true,
+            channel1: PulseChannel::new(true),
+            channel2: PulseChannel::new(false),
+            channel3: WaveChannel::new(),
+            channel4: NoiseChannel::new(),
+            nr50: 0,
+            nr51: 0,
+            frame_sequencer_step: 0,
+            last_div_bit: false,
+            sample_rate: DEFAULT_SAMPLE_RATE,
+            rate_adjust: 1.0,
+            mix_clock: 0,
+            output: StereoOutput::new(DEFAULT_SAMPLE_RATE, 1.0),
+            record_output: None,
+            channel_outputs: None,
+            samples: Vec::new(),
+            record_samples: Vec::new(),
+            channel_samples: Default::default(),
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    // Host output rate, usually 44100 or 48000
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.sample_rate = sample_rate;
+        self.output.set_rates(sample_rate, self.rate_adjust);
+        if let Some(output) = self.record_output.as_mut() {
+            output.set_rates(sample_rate, 1.0);
+        }
+        if let Some(outputs) = self.channel_outputs.as_mut() {
+            for output in outputs.iter_mut() {
+                output.set_rates(sample_rate, 1.0);
+            }
+        }
+    }
+
+    // Produce adjust times as many samples, small changes aren't audible as pitch
+    // only samples is affected, recordings stay at the sample rate
+    pub fn set_rate_adjust(&mut self, adjust: f64) {
+        self.rate_adjust = adjust;
+        self.output.set_rates(self.sample_rate, adjust);
+    }
+
+    // Also fill record_samples with the mix
+    pub fn set_recording(&mut self, recording: bool) {
+        self.record_output = if recording {
+            Some(StereoOutput::new(self.sample_rate, 1.0))
+        } else {
+            None
+        };
+        self.record_samples.clear();
+    }
+
+    // Also fill channel_samples, each channel panned and scaled as in the mix
+    pub fn set_channel_capture(&mut self, capture: bool) {
+        self.channel_outputs = if capture {
+            Some(Box::new(std::array::from_fn(|_| StereoOutput::new(self.sample_rate, 1.0))))
+        } else {
+            None
+        };
+        self.channel_samples = Default::default();
+    }
+
+    pub fn read_register(&self, address: u16) -> u8 {
+        match address {
+            0xFF10..=0xFF14 => self.channel1.read(address - 0xFF10),
+            0xFF16..=0xFF19 => self.channel2.read(address - 0xFF15),
+            0xFF1A..=0xFF1E => self.channel3.read(address - 0xFF1A),
+            0xFF20..=0xFF23 => self.channel4.read(address - 0xFF1F),
+            0xFF24 => self.nr50,
+            0xFF25 => self.nr51,
+            // bits 4 - 6 are unused, bits 0 - 3 report which channels are on
+            0xFF26 => (self.power as u8) << POWER_BYTE_POSITION | 0x70
+                | (self.channel4.enabled as u8) << 3
+                | (self.channel3.enabled as u8) << 2
+                | (self.channel2.enabled as u8) << 1
+                | self.channel1.enabled as u8,
+            0xFF30..=0xFF3F => self.channel3.wave_ram[(address - 0xFF30) as usize],
+            _ => 0xFF,
+        }
+    }
+
+    pub fn write_register(&mut self, address: u16, value: u8) {
+        match address {
+            0xFF26 => self.write_power((value >> POWER_BYTE_POSITION) & 0b1 != 0),
+            0xFF30..=0xFF3F => self.channel3.wave_ram[(address - 0xFF30) as usize] = value,
+            // while powered off only the DMG length counters can be written
+            _ if !self.power => match address {
+                0xFF11 => self.channel1.length.load(value & 0x3F),
+                0xFF16 => self.channel2.length.load(value & 0x3F),
+                0xFF1B => self.channel3.length.load(value),
+                0xFF20 => self.channel4.length.load(value & 0x3F),
+                _ => {},
+            },
+            0xFF10..=0xFF14 => self.channel1.write(address - 0xFF10, value),
+            0xFF16..=0xFF19 => self.channel2.write(address - 0xFF15, value),
+            0xFF1A..=0xFF1E => self.channel3.write(address - 0xFF1A, value),
+            0xFF20..=0xFF23 => self.channel4.write(address - 0xFF1F, value),
+            0xFF24 => self.nr50 = value,
+            0xFF25 => self.nr51 = value,
+            _ => {},
+        }
+    }
+
+    // Powering off clears NR10 - NR51, powering on restarts the frame sequencer
+    fn write_power(&mut self, power: bool) {
+        if self.power && !power {
+            self.channel1.power_off();
+            self.channel2.power_off();
+            self.channel3.power_off();
+            self.channel4.power_off();
+            self.nr50 = 0;
+            self.nr51 = 0;
+        } else if !self.power && power {
+            self.frame_sequencer_step = 0;
+        }
+        self.power = power;
+    }
+
+    // Advance by cycles, div is the DIV register after the same cycles
+    pub fn tick(&mut self, cycles: u8, div: u8) {
+        // writes to DIV can also cause a falling edge
+        let div_bit = (div >> FRAME_SEQUENCER_DIV_BIT) & 0b1 != 0;
+        if self.last_div_bit && !div_bit && self.power {
+            self.clock_frame_sequencer();
+        }
+        self.last_div_bit = div_bit;
+
+        for _ in 0..cycles {
+            self.channel1.tick();
+            self.channel2.tick();
+            self.channel3.tick();
+            self.channel4.tick();
+
+            self.mix_clock += 1;
+            if self.mix_clock == MIX_DIVIDER {
+                self.mix_clock = 0;
+                self.mix();
+            }
+        }
+
+        self.output.read_samples(&mut self.samples);
+        if let Some(output) = self.record_output.as_mut() {
+            output.read_samples(&mut self.record_samples);
+        }
+        if let Some(outputs) = self.channel_outputs.as_mut() {
+            for (output, samples) in outputs.iter_mut().zip(self.channel_samples.iter_mut()) {
+                output.read_samples(samples);
+            }
+        }
+    }
+
+    // step 0, 2, 4, 6 length, 2 and 6 sweep, 7 envelope
+    fn clock_frame_sequencer(&mut self) {
+        let step = self.frame_sequencer_step;
+        if step.is_multiple_of(2) {
+            self.channel1.clock_length();
+            self.channel2.clock_length();
+            self.channel3.clock_length();
+            self.channel4.clock_length();
+        }
+        if step == 2 || step == 6 {
+            self.channel1.clock_sweep();
+        }
+        if step == 7 {
+            self.channel1.clock_envelope();
+            self.channel2.clock_envelope();
+            self.channel4.clock_envelope();
+        }
+        self.frame_sequencer_step = (step + 1) % 8;
+    }
+
+    // Send left and right levels to the outputs, each channel DAC maps 0 - 15 to 1.0 - -1.0
+    fn mix(&mut self) {
+        let dac = |output: u8, enabled: bool| if enabled { 1.0 - output as f32 / 7.5 } else { 0.0 };
+        let dacs = [
+            dac(self.channel1.output(), self.channel1.dac_enabled()),
+            dac(self.channel2.output(), self.channel2.dac_enabled()),
+            dac(self.channel3.output(), self.channel3.dac_enabled()),
+            dac(self.channel4.output(), self.channel4.dac_enabled()),
+        ];
+        let (left, right) = mixer::mix(dacs, self.nr50, self.nr51);
+        self.output.push(left, right);
+        if let Some(output) = self.record_output.as_mut() {
+            output.push(left, right);
+        }
+
+        if let Some(outputs) = self.channel_outputs.as_mut() {
+            for (channel, output) in outputs.iter_mut().enumerate() {
+                let mut solo = [0.0; 4];
+                solo[channel] = dacs[channel];
+                let (left, right) = mixer::mix(solo, self.nr50, self.nr51);
+                output.push(left, right);
+            }
+        }
+    }
+}
+
+fn mix_rate() -> f64 {
+    (CLOCK_RATE / MIX_DIVIDER) as f64
+}
+
+#[cfg(test)]
+mod test_apu;
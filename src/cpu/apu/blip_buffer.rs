@@ -0,0 +1,115 @@
+use std::f64::consts::PI;
+
+// fractional positions each step can start at, and the taps spread over per step
+const PHASE_BITS: u32 = 6;
+const PHASE_COUNT: usize = 1 << PHASE_BITS;
+const KERNEL_WIDTH: usize = 16;
+
+// pass band as a fraction of the output sample rate, just under Nyquist
+const CUTOFF: f64 = 0.45;
+
+// 32.32 fixed point positions in output samples
+const FRACTION_BITS: u32 = 32;
+
+// Band limited resampler, amplitude changes at the input rate are added as
+// windowed sinc impulses at their exact output position, then integrated back
+// into a waveform when read, so square waves don't alias
+pub struct BlipBuffer {
+    kernel: Vec<[f32; KERNEL_WIDTH + 1]>, // impulse response for each phase and one past the last, each sums to 1.0
+    factor: u64, // output samples per input clock
+    position: u64, // time of the next input clock, relative to buffer[0]
+    buffer: Vec<f32>, // deltas waiting to be integrated
+    integrator: f32,
+    amplitude: f32, // last level passed to set_amplitude
+}
+
+impl BlipBuffer {
+    pub fn new(clock_rate: f64, sample_rate: f64) -> BlipBuffer {
+        let mut blip = BlipBuffer {
+            kernel: build_kernel(),
+            factor: 0,
+            position: 0,
+            buffer: vec![0.0; KERNEL_WIDTH + 1],
+            integrator: 0.0,
+            amplitude: 0.0,
+        };
+        blip.set_rates(clock_rate, sample_rate);
+        blip
+    }
+
+    pub fn set_rates(&mut self, clock_rate: f64, sample_rate: f64) {
+        self.factor = (sample_rate / clock_rate * (1u64 << FRACTION_BITS) as f64).round() as u64;
+    }
+
+    // Move the output to amplitude at the current input clock
+    pub fn set_amplitude(&mut self, amplitude: f32) {
+        let delta = amplitude - self.amplitude;
+        if delta == 0.0 {
+            return;
+        }
+        self.amplitude = amplitude;
+
+        let index = (self.position >> FRACTION_BITS) as usize;
+        let phase = ((self.position >> (FRACTION_BITS - PHASE_BITS)) as usize) & (PHASE_COUNT - 1);
+        // blend towards the next phase by the remaining fraction
+        let blend_bits = FRACTION_BITS - PHASE_BITS;
+        let blend = (self.position & ((1 << blend_bits) - 1)) as f32 / (1u64 << blend_bits) as f32;
+        if self.buffer.len() < index + KERNEL_WIDTH + 1 {
+            self.buffer.resize(index + KERNEL_WIDTH + 1, 0.0);
+        }
+        let taps = self.kernel[phase].iter().zip(self.kernel[phase + 1].iter());
+        for (sample, (tap, next_tap)) in self.buffer[index..].iter_mut().zip(taps) {
+            *sample += delta * (tap + (next_tap - tap) * blend);
+        }
+    }
+
+    // Advance one input clock
+    pub fn clock(&mut self) {
+        self.position += self.factor;
+    }
+
+    // Samples no later amplitude change can affect
+    pub fn samples_available(&self) -> usize {
+        (self.position >> FRACTION_BITS) as usize
+    }
+
+    // Remove count finished samples, the output lags the input by half the kernel width
+    pub fn read_samples(&mut self, count: usize) -> impl Iterator<Item = f32> + '_ {
+        let count = count.min(self.samples_available());
+        self.position -= (count as u64) << FRACTION_BITS;
+        if self.buffer.len() < count + KERNEL_WIDTH + 1 {
+            self.buffer.resize(count + KERNEL_WIDTH + 1, 0.0);
+        }
+
+        let integrator = &mut self.integrator;
+        self.buffer.drain(..count).map(move |delta| {
+            *integrator += delta;
+            *integrator
+        })
+    }
+}
+
+// Blackman windowed sinc, centred on KERNEL_WIDTH / 2 and shifted later by the phase
+// phase PHASE_COUNT is a whole sample later, the last tap lets it be blended with
+fn build_kernel() -> Vec<[f32; KERNEL_WIDTH + 1]> {
+    (0..=PHASE_COUNT).map(|phase| {
+        let offset = phase as f64 / PHASE_COUNT as f64;
+        let mut taps = [0.0; KERNEL_WIDTH + 1];
+        for (i, tap) in taps.iter_mut().enumerate() {
+            let x = i as f64 - (KERNEL_WIDTH / 2) as f64 - offset;
+            let sinc = if x == 0.0 { 1.0 } else { (2.0 * PI * CUTOFF * x).sin() / (2.0 * PI * CUTOFF * x) };
+            let n = (x + (KERNEL_WIDTH / 2 + 1) as f64) / (KERNEL_WIDTH + 2) as f64;
+            let window = 0.42 - 0.5 * (2.0 * PI * n).cos() + 0.08 * (4.0 * PI * n).cos();
+            *tap = sinc * window;
+        }
+        let sum: f64 = taps.iter().sum();
+        let mut kernel = [0.0; KERNEL_WIDTH + 1];
+        for (k, tap) in kernel.iter_mut().zip(taps.iter()) {
+            *k = (tap / sum) as f32;
+        }
+        kernel
+    }).collect()
+}
+
+#[cfg(test)]
+mod test_blip_buffer;
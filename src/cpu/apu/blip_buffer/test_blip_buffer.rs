@@ -0,0 +1,30 @@
+use super::*;
+use crate::cpu::apu::{mix_rate, DEFAULT_SAMPLE_RATE};
+
+#[test]
+fn test_blip_step() {
+    let mut blip = BlipBuffer::new(mix_rate(), DEFAULT_SAMPLE_RATE as f64);
+    blip.set_amplitude(1.0);
+    // 1/64 of a second
+    for _ in 0..mix_rate() as u32 / 64 {
+        blip.clock();
+    }
+    let samples: Vec<f32> = blip.read_samples(1000).collect();
+    assert_eq!(samples.len(), 689);
+    // the step is delayed by half the kernel, then settles at the new level
+    assert!(samples[0].abs() < 0.01);
+    assert!(samples[20..].iter().all(|sample| (sample - 1.0).abs() < 0.001));
+    assert_eq!(blip.samples_available(), 0);
+}
+
+#[test]
+fn test_blip_band_limited() {
+    // a 131 kHz square wave is far above Nyquist and should come out as its average
+    let mut blip = BlipBuffer::new(mix_rate(), DEFAULT_SAMPLE_RATE as f64);
+    for clock in 0..mix_rate() as u32 / 64 {
+        blip.set_amplitude(if clock % 8 < 4 { 1.0 } else { 0.0 });
+        blip.clock();
+    }
+    let samples: Vec<f32> = blip.read_samples(689).collect();
+    assert!(samples[20..].iter().all(|sample| (sample - 0.5).abs() < 0.01));
+}
@@ -0,0 +1,59 @@
+const VOLUME_BYTE_POSITION:   u8 = 4;
+const INCREASE_BYTE_POSITION: u8 = 3;
+
+// Volume envelope (NRx2), steps volume up or down at 64 Hz / period
+pub struct Envelope {
+    initial_volume: u8,
+    increase: bool,
+    period: u8, // 0 stops the envelope
+    timer: u8,
+    pub volume: u8,
+}
+
+impl Envelope {
+    pub fn new() -> Envelope {
+        Envelope {
+            initial_volume: 0,
+            increase: false,
+            period: 0,
+            timer: 0,
+            volume: 0,
+        }
+    }
+
+    pub fn read(&self) -> u8 {
+        self.initial_volume << VOLUME_BYTE_POSITION | (self.increase as u8) << INCREASE_BYTE_POSITION | self.period
+    }
+
+    pub fn write(&mut self, value: u8) {
+        self.initial_volume = value >> VOLUME_BYTE_POSITION;
+        self.increase = (value >> INCREASE_BYTE_POSITION) & 0b1 != 0;
+        self.period = value & 0x07;
+    }
+
+    // the DAC is off when the upper 5 bits of NRx2 are all 0
+    pub fn dac_enabled(&self) -> bool {
+        self.initial_volume != 0 || self.increase
+    }
+
+    pub fn trigger(&mut self) {
+        self.volume = self.initial_volume;
+        self.timer = self.period;
+    }
+
+    pub fn clock(&mut self) {
+        if self.period == 0 {
+            return;
+        }
+        self.timer = self.timer.saturating_sub(1);
+        if self.timer > 0 {
+            return;
+        }
+        self.timer = self.period;
+        if self.increase && self.volume < 15 {
+            self.volume += 1;
+        } else if !self.increase && self.volume > 0 {
+            self.volume -= 1;
+        }
+    }
+}
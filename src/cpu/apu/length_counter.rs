@@ -0,0 +1,42 @@
+// Silences a channel after a number of 256 Hz frame sequencer clocks
+#[derive(Copy, Clone)]
+pub struct LengthCounter {
+    pub enabled: bool,
+    counter: u16,
+    max: u16, // 64 for most channels, 256 for the wave channel
+}
+
+impl LengthCounter {
+    pub fn new(max: u16) -> LengthCounter {
+        LengthCounter {
+            enabled: false,
+            counter: 0,
+            max,
+        }
+    }
+
+    // NRx1 holds max - length
+    pub fn load(&mut self, value: u8) {
+        self.counter = self.max - value as u16;
+    }
+
+    pub fn trigger(&mut self) {
+        if self.counter == 0 {
+            self.counter = self.max;
+        }
+    }
+
+    // DMG keeps the count through APU power off, only the enable in NRx4 is cleared
+    pub fn power_off(&mut self) {
+        self.enabled = false;
+    }
+
+    // Returns true when the counter runs out and the channel should turn off
+    pub fn clock(&mut self) -> bool {
+        if self.enabled && self.counter > 0 {
+            self.counter -= 1;
+            return self.counter == 0;
+        }
+        false
+    }
+}
@@ -0,0 +1,110 @@
+use super::blip_buffer::BlipBuffer;
+use super::{mix_rate, CLOCK_RATE};
+
+const LEFT_VOLUME_BYTE_POSITION: u8 = 4;
+const LEFT_PAN_BYTE_POSITION:    u8 = 4;
+
+// capacitor charge kept per CPU cycle by the DMG output stage
+const HIGH_PASS_CHARGE_PER_CYCLE: f64 = 0.999958;
+
+// Mix the four channel DAC outputs (-1.0 - 1.0) into left and right
+// NR51 bits 4 - 7 send channels 1 - 4 left, bits 0 - 3 send them right
+// NR50 bits 4 - 6 are the left volume and bits 0 - 2 the right, volume 0 still plays at 1/8
+pub fn mix(dacs: [f32; 4], nr50: u8, nr51: u8) -> (f32, f32) {
+    let mut left = 0.0;
+    let mut right = 0.0;
+    for (channel, dac) in dacs.iter().enumerate() {
+        if (nr51 >> (channel as u8 + LEFT_PAN_BYTE_POSITION)) & 0b1 != 0 {
+            left += dac;
+        }
+        if (nr51 >> channel) & 0b1 != 0 {
+            right += dac;
+        }
+    }
+
+    let left_volume = ((nr50 >> LEFT_VOLUME_BYTE_POSITION) & 0x07) as f32 + 1.0;
+    let right_volume = (nr50 & 0x07) as f32 + 1.0;
+    (left / 4.0 * left_volume / 8.0, right / 4.0 * right_volume / 8.0)
+}
+
+// DC blocking capacitor on each output, removes the offset left by DACs that are on but quiet
+pub struct HighPassFilter {
+    capacitor: f32,
+    charge: f32, // fraction of the capacitor kept per output sample
+}
+
+impl HighPassFilter {
+    pub fn new(clock_rate: u32, sample_rate: u32) -> HighPassFilter {
+        let mut filter = HighPassFilter {
+            capacitor: 0.0,
+            charge: 0.0,
+        };
+        filter.set_sample_rate(clock_rate, sample_rate);
+        filter
+    }
+
+    pub fn set_sample_rate(&mut self, clock_rate: u32, sample_rate: u32) {
+        let cycles_per_sample = clock_rate as f64 / sample_rate as f64;
+        self.charge = HIGH_PASS_CHARGE_PER_CYCLE.powf(cycles_per_sample) as f32;
+    }
+
+    pub fn filter(&mut self, input: f32) -> f32 {
+        let output = input - self.capacitor;
+        self.capacitor = input - output * self.charge;
+        output
+    }
+}
+
+// Resamples left and right levels at the mix rate down to the host rate, then blocks DC
+pub struct StereoOutput {
+    blip_left: BlipBuffer,
+    blip_right: BlipBuffer,
+    high_pass_left: HighPassFilter,
+    high_pass_right: HighPassFilter,
+}
+
+impl StereoOutput {
+    pub fn new(sample_rate: u32, rate_adjust: f64) -> StereoOutput {
+        let output_rate = sample_rate as f64 * rate_adjust;
+        StereoOutput {
+            blip_left: BlipBuffer::new(mix_rate(), output_rate),
+            blip_right: BlipBuffer::new(mix_rate(), output_rate),
+            high_pass_left: HighPassFilter::new(CLOCK_RATE, sample_rate),
+            high_pass_right: HighPassFilter::new(CLOCK_RATE, sample_rate),
+        }
+    }
+
+    // rate_adjust scales the resampling ratio without changing the filter
+    pub fn set_rates(&mut self, sample_rate: u32, rate_adjust: f64) {
+        let output_rate = sample_rate as f64 * rate_adjust;
+        self.blip_left.set_rates(mix_rate(), output_rate);
+        self.blip_right.set_rates(mix_rate(), output_rate);
+        self.high_pass_left.set_sample_rate(CLOCK_RATE, sample_rate);
+        self.high_pass_right.set_sample_rate(CLOCK_RATE, sample_rate);
+    }
+
+    // Levels for one mix clock
+    pub fn push(&mut self, left: f32, right: f32) {
+        self.blip_left.set_amplitude(left);
+        self.blip_right.set_amplitude(right);
+        self.blip_left.clock();
+        self.blip_right.clock();
+    }
+
+    // Append finished samples interleaved left and right
+    pub fn read_samples(&mut self, samples: &mut Vec<f32>) {
+        let count = self.blip_left.samples_available();
+        if count == 0 {
+            return;
+        }
+        let left = self.blip_left.read_samples(count);
+        let right = self.blip_right.read_samples(count);
+        for (left, right) in left.zip(right) {
+            samples.push(self.high_pass_left.filter(left));
+            samples.push(self.high_pass_right.filter(right));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_mixer;
@@ -0,0 +1,27 @@
+use super::*;
+use crate::cpu::apu::DEFAULT_SAMPLE_RATE;
+
+#[test]
+fn test_mix_panning() {
+    let dacs = [1.0, 0.5, -0.5, -1.0];
+    // everything to both sides at full volume
+    assert_eq!(mix(dacs, 0x77, 0xFF), (0.0, 0.0));
+    // channel 1 left, channel 2 right
+    assert_eq!(mix(dacs, 0x77, 0x21), (0.125, 0.25));
+    // volume 0 still plays at 1/8, the Vin bits are ignored
+    assert_eq!(mix(dacs, 0x88, 0x11), (0.03125, 0.03125));
+    assert_eq!(mix(dacs, 0x70, 0x11), (0.25, 0.03125));
+    assert_eq!(mix(dacs, 0x77, 0x00), (0.0, 0.0));
+}
+
+#[test]
+fn test_high_pass_filter() {
+    let mut filter = HighPassFilter::new(CLOCK_RATE, DEFAULT_SAMPLE_RATE);
+    assert_eq!(filter.filter(1.0), 1.0);
+    // a constant level decays towards zero
+    let mut output = 1.0;
+    for _ in 0..DEFAULT_SAMPLE_RATE {
+        output = filter.filter(1.0);
+    }
+    assert!(output.abs() < 0.001);
+}
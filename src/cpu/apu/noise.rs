@@ -0,0 +1,130 @@
+use super::envelope::Envelope;
+use super::length_counter::LengthCounter;
+
+// base periods in cycles for NR43 divisor codes 0 - 7
+const DIVISORS: [u16; 8] = [8, 16, 32, 48, 64, 80, 96, 112];
+
+const CLOCK_SHIFT_BYTE_POSITION:   u8 = 4;
+const WIDTH_MODE_BYTE_POSITION:    u8 = 3;
+const TRIGGER_BYTE_POSITION:       u8 = 7;
+const LENGTH_ENABLE_BYTE_POSITION: u8 = 6;
+
+// Channel 4, pseudo random noise from a linear feedback shift register
+// NR41 length, NR42 envelope, NR43 clock shift, width and divisor, NR44 trigger
+pub struct NoiseChannel {
+    pub enabled: bool,
+    clock_shift: u8,
+    width_mode: bool, // 7 bit LFSR when set, 15 bit otherwise
+    divisor_code: u8,
+    timer: u32,
+    lfsr: u16,
+    pub length: LengthCounter,
+    pub envelope: Envelope,
+}
+
+impl NoiseChannel {
+    pub fn new() -> NoiseChannel {
+        NoiseChannel {
+            enabled: false,
+            clock_shift: 0,
+            width_mode: false,
+            divisor_code: 0,
+            timer: 0,
+            lfsr: 0x7FFF,
+            length: LengthCounter::new(64),
+            envelope: Envelope::new(),
+        }
+    }
+
+    // register is 1 - 4 for NR41 - NR44, write only bits read back as 1
+    pub fn read(&self, register: u16) -> u8 {
+        match register {
+            1 => 0xFF,
+            2 => self.envelope.read(),
+            3 => self.clock_shift << CLOCK_SHIFT_BYTE_POSITION
+                | (self.width_mode as u8) << WIDTH_MODE_BYTE_POSITION
+                | self.divisor_code,
+            _ => (self.length.enabled as u8) << LENGTH_ENABLE_BYTE_POSITION | 0xBF,
+        }
+    }
+
+    pub fn write(&mut self, register: u16, value: u8) {
+        match register {
+            1 => self.length.load(value & 0x3F),
+            2 => {
+                self.envelope.write(value);
+                if !self.envelope.dac_enabled() {
+                    self.enabled = false;
+                }
+            },
+            3 => {
+                self.clock_shift = value >> CLOCK_SHIFT_BYTE_POSITION;
+                self.width_mode = (value >> WIDTH_MODE_BYTE_POSITION) & 0b1 != 0;
+                self.divisor_code = value & 0x07;
+            },
+            _ => {
+                self.length.enabled = (value >> LENGTH_ENABLE_BYTE_POSITION) & 0b1 != 0;
+                if (value >> TRIGGER_BYTE_POSITION) & 0b1 != 0 {
+                    self.trigger();
+                }
+            },
+        }
+    }
+
+    fn trigger(&mut self) {
+        self.enabled = self.envelope.dac_enabled();
+        self.length.trigger();
+        self.timer = self.period();
+        self.envelope.trigger();
+        self.lfsr = 0x7FFF;
+    }
+
+    // NR41 - NR44 read back as after reset, the LFSR is reseeded on the next trigger anyway
+    pub fn power_off(&mut self) {
+        self.length.power_off();
+        let length = self.length;
+        *self = NoiseChannel::new();
+        self.length = length;
+    }
+
+    fn period(&self) -> u32 {
+        (DIVISORS[self.divisor_code as usize] as u32) << self.clock_shift
+    }
+
+    // Advance one cycle, the LFSR shifts when the timer runs out
+    pub fn tick(&mut self) {
+        self.timer = self.timer.saturating_sub(1);
+        if self.timer > 0 {
+            return;
+        }
+        self.timer = self.period();
+
+        let feedback = (self.lfsr ^ (self.lfsr >> 1)) & 0b1;
+        self.lfsr = (self.lfsr >> 1) | (feedback << 14);
+        if self.width_mode {
+            self.lfsr = (self.lfsr & !(1 << 6)) | (feedback << 6);
+        }
+    }
+
+    pub fn clock_length(&mut self) {
+        if self.length.clock() {
+            self.enabled = false;
+        }
+    }
+
+    pub fn clock_envelope(&mut self) {
+        self.envelope.clock();
+    }
+
+    pub fn dac_enabled(&self) -> bool {
+        self.envelope.dac_enabled()
+    }
+
+    // Digital output 0 - 15, the volume plays while bit 0 of the LFSR is clear
+    pub fn output(&self) -> u8 {
+        if !self.enabled || self.lfsr & 0b1 != 0 {
+            return 0;
+        }
+        self.envelope.volume
+    }
+}
@@ -0,0 +1,143 @@
+use super::envelope::Envelope;
+use super::length_counter::LengthCounter;
+use super::sweep::Sweep;
+
+// 8 step waveforms for duty 12.5%, 25%, 50% and 75%
+const DUTY_PATTERNS: [[u8; 8]; 4] = [
+    [0, 0, 0, 0, 0, 0, 0, 1],
+    [1, 0, 0, 0, 0, 0, 0, 1],
+    [1, 0, 0, 0, 0, 1, 1, 1],
+    [0, 1, 1, 1, 1, 1, 1, 0],
+];
+
+const DUTY_BYTE_POSITION:          u8 = 6;
+const TRIGGER_BYTE_POSITION:       u8 = 7;
+const LENGTH_ENABLE_BYTE_POSITION: u8 = 6;
+
+// Square wave channels 1 and 2, only channel 1 has a sweep
+// NRx0 sweep, NRx1 duty and length, NRx2 envelope, NRx3 frequency low, NRx4 trigger and frequency high
+pub struct PulseChannel {
+    pub enabled: bool,
+    duty: u8,
+    duty_position: u8,
+    frequency: u16, // 11 bits, the period is (2048 - frequency) * 4 cycles
+    timer: u16,
+    pub length: LengthCounter,
+    pub envelope: Envelope,
+    pub sweep: Option<Sweep>,
+}
+
+impl PulseChannel {
+    pub fn new(has_sweep: bool) -> PulseChannel {
+        PulseChannel {
+            enabled: false,
+            duty: 0,
+            duty_position: 0,
+            frequency: 0,
+            timer: 0,
+            length: LengthCounter::new(64),
+            envelope: Envelope::new(),
+            sweep: if has_sweep { Some(Sweep::new()) } else { None },
+        }
+    }
+
+    // register is 0 - 4 for NRx0 - NRx4, write only bits read back as 1
+    pub fn read(&self, register: u16) -> u8 {
+        match register {
+            0 => self.sweep.as_ref().map(|sweep| sweep.read()).unwrap_or(0xFF),
+            1 => self.duty << DUTY_BYTE_POSITION | 0x3F,
+            2 => self.envelope.read(),
+            3 => 0xFF,
+            _ => (self.length.enabled as u8) << LENGTH_ENABLE_BYTE_POSITION | 0xBF,
+        }
+    }
+
+    pub fn write(&mut self, register: u16, value: u8) {
+        match register {
+            0 => {
+                if let Some(sweep) = &mut self.sweep {
+                    if !sweep.write(value) {
+                        self.enabled = false;
+                    }
+                }
+            },
+            1 => {
+                self.duty = value >> DUTY_BYTE_POSITION;
+                self.length.load(value & 0x3F);
+            },
+            2 => {
+                self.envelope.write(value);
+                if !self.envelope.dac_enabled() {
+                    self.enabled = false;
+                }
+            },
+            3 => self.frequency = (self.frequency & 0x700) | value as u16,
+            _ => {
+                self.frequency = (self.frequency & 0xFF) | ((value as u16 & 0x07) << 8);
+                self.length.enabled = (value >> LENGTH_ENABLE_BYTE_POSITION) & 0b1 != 0;
+                if (value >> TRIGGER_BYTE_POSITION) & 0b1 != 0 {
+                    self.trigger();
+                }
+            },
+        }
+    }
+
+    fn trigger(&mut self) {
+        self.enabled = self.envelope.dac_enabled();
+        self.length.trigger();
+        self.timer = (2048 - self.frequency) * 4;
+        self.envelope.trigger();
+        if let Some(sweep) = &mut self.sweep {
+            if !sweep.trigger(self.frequency) {
+                self.enabled = false;
+            }
+        }
+    }
+
+    // NRx0 - NRx4 read back as after reset
+    pub fn power_off(&mut self) {
+        self.length.power_off();
+        let length = self.length;
+        *self = PulseChannel::new(self.sweep.is_some());
+        self.length = length;
+    }
+
+    // Advance one cycle, the duty position steps when the frequency timer runs out
+    pub fn tick(&mut self) {
+        self.timer = self.timer.saturating_sub(1);
+        if self.timer == 0 {
+            self.timer = (2048 - self.frequency) * 4;
+            self.duty_position = (self.duty_position + 1) % 8;
+        }
+    }
+
+    pub fn clock_length(&mut self) {
+        if self.length.clock() {
+            self.enabled = false;
+        }
+    }
+
+    pub fn clock_envelope(&mut self) {
+        self.envelope.clock();
+    }
+
+    pub fn clock_sweep(&mut self) {
+        if let Some(sweep) = &mut self.sweep {
+            if !sweep.clock(&mut self.frequency) {
+                self.enabled = false;
+            }
+        }
+    }
+
+    pub fn dac_enabled(&self) -> bool {
+        self.envelope.dac_enabled()
+    }
+
+    // Digital output 0 - 15
+    pub fn output(&self) -> u8 {
+        if !self.enabled {
+            return 0;
+        }
+        DUTY_PATTERNS[self.duty as usize][self.duty_position as usize] * self.envelope.volume
+    }
+}
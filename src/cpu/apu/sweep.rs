@@ -0,0 +1,86 @@
+const PERIOD_BYTE_POSITION: u8 = 4;
+const NEGATE_BYTE_POSITION: u8 = 3;
+
+// Channel 1 frequency sweep (NR10), clocked at 128 Hz
+pub struct Sweep {
+    period: u8,
+    negate: bool,
+    shift: u8,
+    enabled: bool,
+    timer: u8,
+    shadow_frequency: u16,
+    negated: bool, // a subtraction happened since the last trigger
+}
+
+impl Sweep {
+    pub fn new() -> Sweep {
+        Sweep {
+            period: 0,
+            negate: false,
+            shift: 0,
+            enabled: false,
+            timer: 0,
+            shadow_frequency: 0,
+            negated: false,
+        }
+    }
+
+    pub fn read(&self) -> u8 {
+        0x80 | self.period << PERIOD_BYTE_POSITION | (self.negate as u8) << NEGATE_BYTE_POSITION | self.shift
+    }
+
+    // Returns false when clearing negate after a subtraction disables the channel
+    pub fn write(&mut self, value: u8) -> bool {
+        self.period = (value >> PERIOD_BYTE_POSITION) & 0x07;
+        self.negate = (value >> NEGATE_BYTE_POSITION) & 0b1 != 0;
+        self.shift = value & 0x07;
+        self.negate || !self.negated
+    }
+
+    // Returns false when the first overflow check disables the channel
+    pub fn trigger(&mut self, frequency: u16) -> bool {
+        self.shadow_frequency = frequency;
+        self.timer = self.reload_value();
+        self.enabled = self.period != 0 || self.shift != 0;
+        self.negated = false;
+        self.shift == 0 || self.next_frequency() <= 0x7FF
+    }
+
+    // Updates frequency, returns false when the sweep overflows and disables the channel
+    pub fn clock(&mut self, frequency: &mut u16) -> bool {
+        self.timer = self.timer.saturating_sub(1);
+        if self.timer > 0 {
+            return true;
+        }
+        self.timer = self.reload_value();
+        if !self.enabled || self.period == 0 {
+            return true;
+        }
+
+        let next = self.next_frequency();
+        if next > 0x7FF {
+            return false;
+        }
+        if self.shift != 0 {
+            self.shadow_frequency = next;
+            *frequency = next;
+        }
+        // the new frequency is checked again but not written back
+        self.next_frequency() <= 0x7FF
+    }
+
+    // period 0 is treated as 8 for the timer
+    fn reload_value(&self) -> u8 {
+        if self.period == 0 { 8 } else { self.period }
+    }
+
+    fn next_frequency(&mut self) -> u16 {
+        let delta = self.shadow_frequency >> self.shift;
+        if self.negate {
+            self.negated = true;
+            self.shadow_frequency - delta
+        } else {
+            self.shadow_frequency + delta
+        }
+    }
+}
@@ -0,0 +1,288 @@
+use super::*;
+
+// DIV values that make the frame sequencer take one step
+fn clock_frame_sequencer(apu: &mut APU) {
+    apu.tick(0, 0x10);
+    apu.tick(0, 0x00);
+}
+
+#[test]
+fn test_register_read_masks() {
+    let mut apu = APU::new();
+    apu.write_register(0xFF10, 0x7F);
+    assert_eq!(apu.read_register(0xFF10), 0xFF);
+    apu.write_register(0xFF11, 0x80);
+    assert_eq!(apu.read_register(0xFF11), 0xBF);
+    apu.write_register(0xFF12, 0xF3);
+    assert_eq!(apu.read_register(0xFF12), 0xF3);
+    apu.write_register(0xFF13, 0x12);
+    assert_eq!(apu.read_register(0xFF13), 0xFF);
+    apu.write_register(0xFF14, 0x40);
+    assert_eq!(apu.read_register(0xFF14), 0xFF);
+    apu.write_register(0xFF14, 0x00);
+    assert_eq!(apu.read_register(0xFF14), 0xBF);
+    assert_eq!(apu.read_register(0xFF15), 0xFF);
+}
+
+#[test]
+fn test_trigger_and_status() {
+    let mut apu = APU::new();
+    assert_eq!(apu.read_register(0xFF26), 0xF0);
+
+    // trigger with the DAC off leaves the channel off
+    apu.write_register(0xFF19, 0x80);
+    assert_eq!(apu.read_register(0xFF26), 0xF0);
+
+    apu.write_register(0xFF17, 0xF0);
+    apu.write_register(0xFF19, 0x80);
+    assert_eq!(apu.read_register(0xFF26), 0xF2);
+
+    // turning the DAC off disables the channel
+    apu.write_register(0xFF17, 0x00);
+    assert_eq!(apu.read_register(0xFF26), 0xF0);
+}
+
+#[test]
+fn test_length_counter() {
+    let mut apu = APU::new();
+    apu.write_register(0xFF12, 0xF0);
+    // length 64 - 62 = 2
+    apu.write_register(0xFF11, 62);
+    apu.write_register(0xFF14, 0xC0);
+    assert!(apu.channel1.enabled);
+
+    // length clocks on even steps
+    clock_frame_sequencer(&mut apu);
+    assert!(apu.channel1.enabled);
+    clock_frame_sequencer(&mut apu);
+    clock_frame_sequencer(&mut apu);
+    assert!(!apu.channel1.enabled);
+}
+
+#[test]
+fn test_envelope() {
+    let mut apu = APU::new();
+    // volume 2, decreasing every step
+    apu.write_register(0xFF17, 0x21);
+    apu.write_register(0xFF19, 0x80);
+    assert_eq!(apu.channel2.envelope.volume, 2);
+
+    for _ in 0..8 {
+        clock_frame_sequencer(&mut apu);
+    }
+    assert_eq!(apu.channel2.envelope.volume, 1);
+    for _ in 0..16 {
+        clock_frame_sequencer(&mut apu);
+    }
+    assert_eq!(apu.channel2.envelope.volume, 0);
+    // the DAC stays on so the channel does too
+    assert!(apu.channel2.enabled);
+}
+
+#[test]
+fn test_sweep_overflow() {
+    let mut apu = APU::new();
+    apu.write_register(0xFF12, 0xF0);
+    // period 1, adding, shift 1
+    apu.write_register(0xFF10, 0x11);
+    apu.write_register(0xFF13, 0x00);
+    // 0x500 + 0x280 overflows on the first calculation after one update
+    apu.write_register(0xFF14, 0x85);
+    assert!(apu.channel1.enabled);
+
+    // sweep clocks on steps 2 and 6
+    for _ in 0..3 {
+        clock_frame_sequencer(&mut apu);
+    }
+    assert!(!apu.channel1.enabled);
+
+    // triggering with an immediate overflow disables straight away
+    apu.write_register(0xFF10, 0x01);
+    apu.write_register(0xFF13, 0xFF);
+    apu.write_register(0xFF14, 0x87);
+    assert!(!apu.channel1.enabled);
+}
+
+#[test]
+fn test_sweep_negate_clear() {
+    let mut apu = APU::new();
+    apu.write_register(0xFF12, 0xF0);
+    apu.write_register(0xFF10, 0x19);
+    apu.write_register(0xFF14, 0x84);
+    assert!(apu.channel1.enabled);
+
+    // leaving negate mode after a subtraction disables the channel
+    apu.write_register(0xFF10, 0x11);
+    assert!(!apu.channel1.enabled);
+}
+
+#[test]
+fn test_duty_output() {
+    let mut apu = APU::new();
+    // 50% duty, volume 15, frequency 2047 for a 4 cycle step
+    apu.write_register(0xFF16, 0x80);
+    apu.write_register(0xFF17, 0xF0);
+    apu.write_register(0xFF18, 0xFF);
+    apu.write_register(0xFF19, 0x87);
+
+    let mut outputs = Vec::new();
+    for _ in 0..8 {
+        apu.tick(4, 0);
+        outputs.push(apu.channel2.output());
+    }
+    // positions 1 - 7 then back to 0
+    assert_eq!(outputs, vec![0, 0, 0, 0, 15, 15, 15, 15]);
+}
+
+#[test]
+fn test_sample_rate() {
+    let mut apu = APU::new();
+    for _ in 0..CLOCK_RATE / 16 {
+        apu.tick(16, 0);
+    }
+    assert_eq!(apu.samples.len(), DEFAULT_SAMPLE_RATE as usize * 2);
+}
+
+#[test]
+fn test_wave_output() {
+    let mut apu = APU::new();
+    apu.write_register(0xFF30, 0x9A);
+    apu.write_register(0xFF1A, 0x80);
+    apu.write_register(0xFF1C, 0x20);
+    // frequency 2047 plays a new sample every 2 cycles
+    apu.write_register(0xFF1D, 0xFF);
+    apu.write_register(0xFF1E, 0x87);
+    assert_eq!(apu.read_register(0xFF26), 0xF4);
+    assert_eq!(apu.channel3.output(), 0x9);
+    apu.tick(2, 0);
+    assert_eq!(apu.channel3.output(), 0xA);
+
+    // 50% and 25% shift the sample right
+    apu.write_register(0xFF1C, 0x40);
+    assert_eq!(apu.channel3.output(), 0x5);
+    apu.write_register(0xFF1C, 0x60);
+    assert_eq!(apu.channel3.output(), 0x2);
+    apu.write_register(0xFF1C, 0x00);
+    assert_eq!(apu.channel3.output(), 0x0);
+
+    // NR30 turns the DAC and the channel off
+    apu.write_register(0xFF1A, 0x00);
+    assert_eq!(apu.read_register(0xFF26), 0xF0);
+}
+
+#[test]
+fn test_wave_ram_access() {
+    let mut apu = APU::new();
+    for i in 0..16 {
+        apu.write_register(0xFF30 + i, i as u8 * 0x11);
+    }
+    for i in 0..16 {
+        assert_eq!(apu.read_register(0xFF30 + i), i as u8 * 0x11);
+    }
+    assert_eq!(apu.read_register(0xFF1F), 0xFF);
+    assert_eq!(apu.read_register(0xFF27), 0xFF);
+}
+
+// outputs of channel 4 after each of 254 LFSR shifts
+fn noise_outputs(apu: &mut APU) -> Vec<u8> {
+    apu.write_register(0xFF21, 0xF0);
+    apu.write_register(0xFF23, 0x80);
+    (0..254).map(|_| {
+        apu.tick(8, 0);
+        apu.channel4.output()
+    }).collect()
+}
+
+#[test]
+fn test_noise_lfsr() {
+    let mut apu = APU::new();
+    apu.write_register(0xFF22, 0x00);
+    let outputs = noise_outputs(&mut apu);
+    assert!(outputs.contains(&15));
+    assert!(outputs.contains(&0));
+    assert_ne!(outputs[..127], outputs[127..]);
+
+    // width mode repeats every 127 shifts
+    apu.write_register(0xFF22, 0x08);
+    let outputs = noise_outputs(&mut apu);
+    assert_eq!(outputs[..127], outputs[127..]);
+    assert_eq!(apu.read_register(0xFF22), 0x08);
+}
+
+#[test]
+fn test_power_off() {
+    let mut apu = APU::new();
+    apu.write_register(0xFF12, 0xF0);
+    apu.write_register(0xFF14, 0x80);
+    apu.write_register(0xFF1A, 0x80);
+    apu.write_register(0xFF1E, 0x80);
+    apu.write_register(0xFF24, 0x77);
+    apu.write_register(0xFF25, 0xF3);
+    apu.write_register(0xFF30, 0x12);
+    assert_eq!(apu.read_register(0xFF26), 0xF5);
+
+    apu.write_register(0xFF26, 0x00);
+    assert_eq!(apu.read_register(0xFF26), 0x70);
+    assert_eq!(apu.read_register(0xFF12), 0x00);
+    assert_eq!(apu.read_register(0xFF1A), 0x7F);
+    assert_eq!(apu.read_register(0xFF24), 0x00);
+    assert_eq!(apu.read_register(0xFF25), 0x00);
+    // wave RAM survives
+    assert_eq!(apu.read_register(0xFF30), 0x12);
+
+    // writes are ignored while off, except length and wave RAM
+    apu.write_register(0xFF12, 0xF0);
+    apu.write_register(0xFF24, 0x77);
+    apu.write_register(0xFF30, 0x34);
+    apu.write_register(0xFF11, 0xBF);
+    assert_eq!(apu.read_register(0xFF12), 0x00);
+    assert_eq!(apu.read_register(0xFF24), 0x00);
+    assert_eq!(apu.read_register(0xFF30), 0x34);
+
+    // the loaded length of 1 runs out on the first length clock after power on
+    apu.write_register(0xFF26, 0x80);
+    apu.write_register(0xFF12, 0xF0);
+    apu.write_register(0xFF14, 0xC0);
+    assert_eq!(apu.read_register(0xFF26), 0xF1);
+    clock_frame_sequencer(&mut apu);
+    assert_eq!(apu.read_register(0xFF26), 0xF0);
+}
+
+#[test]
+fn test_stereo_output() {
+    let mut apu = APU::new();
+    apu.set_sample_rate(48000);
+    assert_eq!(apu.sample_rate(), 48000);
+    apu.write_register(0xFF24, 0x77);
+    // channel 2 left only, with the DAC on at volume 0 for a constant level
+    apu.write_register(0xFF25, 0x20);
+    apu.write_register(0xFF17, 0x08);
+    apu.write_register(0xFF19, 0x80);
+    // 1/64 of a second
+    for _ in 0..CLOCK_RATE / 64 / 16 {
+        apu.tick(16, 0);
+    }
+    assert_eq!(apu.samples.len(), 750 * 2);
+    // the high pass filter starts charging towards the constant level
+    assert!(apu.samples[100] > 0.1);
+    assert_eq!(apu.samples[101], 0.0);
+}
+
+#[test]
+fn test_rate_adjust() {
+    let mut apu = APU::new();
+    apu.set_rate_adjust(1.005);
+    // 1/64 of a second, 689.06 samples at 44.1 kHz
+    for _ in 0..CLOCK_RATE / 64 / 16 {
+        apu.tick(16, 0);
+    }
+    assert_eq!(apu.samples.len(), 692 * 2);
+
+    // the adjustment survives a sample rate change
+    apu.samples.clear();
+    apu.set_sample_rate(48000);
+    for _ in 0..CLOCK_RATE / 64 / 16 {
+        apu.tick(16, 0);
+    }
+    assert_eq!(apu.samples.len(), 754 * 2);
+}
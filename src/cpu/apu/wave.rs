@@ -0,0 +1,111 @@
+use super::length_counter::LengthCounter;
+
+const DAC_BYTE_POSITION:           u8 = 7;
+const OUTPUT_LEVEL_BYTE_POSITION:  u8 = 5;
+const TRIGGER_BYTE_POSITION:       u8 = 7;
+const LENGTH_ENABLE_BYTE_POSITION: u8 = 6;
+
+// Channel 3, plays 32 4 bit samples from wave RAM at 0xFF30 - 0xFF3F
+// NR30 DAC enable, NR31 length, NR32 output level, NR33 frequency low, NR34 trigger and frequency high
+pub struct WaveChannel {
+    pub enabled: bool,
+    dac_enabled: bool,
+    output_level: u8, // 0 mute, 1 100%, 2 50%, 3 25%
+    frequency: u16, // 11 bits, the period is (2048 - frequency) * 2 cycles
+    timer: u16,
+    position: u8, // sample 0 - 31, the high nibble of each byte plays first
+    pub wave_ram: [u8; 16],
+    pub length: LengthCounter,
+}
+
+impl WaveChannel {
+    pub fn new() -> WaveChannel {
+        WaveChannel {
+            enabled: false,
+            dac_enabled: false,
+            output_level: 0,
+            frequency: 0,
+            timer: 0,
+            position: 0,
+            wave_ram: [0; 16],
+            length: LengthCounter::new(256),
+        }
+    }
+
+    // register is 0 - 4 for NR30 - NR34, write only bits read back as 1
+    pub fn read(&self, register: u16) -> u8 {
+        match register {
+            0 => (self.dac_enabled as u8) << DAC_BYTE_POSITION | 0x7F,
+            1 => 0xFF,
+            2 => self.output_level << OUTPUT_LEVEL_BYTE_POSITION | 0x9F,
+            3 => 0xFF,
+            _ => (self.length.enabled as u8) << LENGTH_ENABLE_BYTE_POSITION | 0xBF,
+        }
+    }
+
+    pub fn write(&mut self, register: u16, value: u8) {
+        match register {
+            0 => {
+                self.dac_enabled = (value >> DAC_BYTE_POSITION) & 0b1 != 0;
+                if !self.dac_enabled {
+                    self.enabled = false;
+                }
+            },
+            1 => self.length.load(value),
+            2 => self.output_level = (value >> OUTPUT_LEVEL_BYTE_POSITION) & 0b11,
+            3 => self.frequency = (self.frequency & 0x700) | value as u16,
+            _ => {
+                self.frequency = (self.frequency & 0xFF) | ((value as u16 & 0x07) << 8);
+                self.length.enabled = (value >> LENGTH_ENABLE_BYTE_POSITION) & 0b1 != 0;
+                if (value >> TRIGGER_BYTE_POSITION) & 0b1 != 0 {
+                    self.trigger();
+                }
+            },
+        }
+    }
+
+    fn trigger(&mut self) {
+        self.enabled = self.dac_enabled;
+        self.length.trigger();
+        self.timer = (2048 - self.frequency) * 2;
+        self.position = 0;
+    }
+
+    // NR30 - NR34 are reset, wave RAM isn't part of the APU's registers and survives
+    pub fn power_off(&mut self) {
+        self.length.power_off();
+        let (length, wave_ram) = (self.length, self.wave_ram);
+        *self = WaveChannel::new();
+        self.length = length;
+        self.wave_ram = wave_ram;
+    }
+
+    // Advance one cycle, the next sample plays when the frequency timer runs out
+    pub fn tick(&mut self) {
+        self.timer = self.timer.saturating_sub(1);
+        if self.timer == 0 {
+            self.timer = (2048 - self.frequency) * 2;
+            self.position = (self.position + 1) % 32;
+        }
+    }
+
+    pub fn clock_length(&mut self) {
+        if self.length.clock() {
+            self.enabled = false;
+        }
+    }
+
+    pub fn dac_enabled(&self) -> bool {
+        self.dac_enabled
+    }
+
+    // Digital output 0 - 15
+    pub fn output(&self) -> u8 {
+        if !self.enabled || self.output_level == 0 {
+            return 0;
+        }
+        let byte = self.wave_ram[self.position as usize / 2];
+        let sample = if self.position.is_multiple_of(2) { byte >> 4 } else { byte & 0x0F };
+        sample >> (self.output_level - 1)
+    }
+}
@@ -0,0 +1,441 @@
+use std::{fmt, fs, io};
+use std::path::{Path, PathBuf};
+
+pub mod mbc1;
+pub use self::mbc1::MBC1;
+
+pub mod mbc2;
+pub use self::mbc2::MBC2;
+
+pub mod mbc3;
+pub use self::mbc3::MBC3;
+
+pub mod mbc5;
+pub use self::mbc5::MBC5;
+
+pub mod real_time_clock;
+use self::real_time_clock::{RealTimeClock, RTC_SAVE_SIZE_SHORT};
+
+// Cartridge header lives at 0x0100 - 0x014F
+const HEADER_END:              usize = 0x0150;
+const TITLE_START:             usize = 0x0134;
+const TITLE_END:               usize = 0x0144;
+const CGB_FLAG_ADDRESS:        usize = 0x0143;
+const NEW_LICENSEE_ADDRESS:    usize = 0x0144;
+const SGB_FLAG_ADDRESS:        usize = 0x0146;
+const CARTRIDGE_TYPE_ADDRESS:  usize = 0x0147;
+const ROM_SIZE_ADDRESS:        usize = 0x0148;
+const RAM_SIZE_ADDRESS:        usize = 0x0149;
+const DESTINATION_ADDRESS:     usize = 0x014A;
+const OLD_LICENSEE_ADDRESS:    usize = 0x014B;
+const HEADER_CHECKSUM_ADDRESS: usize = 0x014D;
+const GLOBAL_CHECKSUM_ADDRESS: usize = 0x014E;
+
+// old licensee code that defers to the two character new licensee code
+const USE_NEW_LICENSEE: u8 = 0x33;
+
+#[derive(Debug)]
+pub enum CartridgeError {
+    Io(io::Error),
+    Truncated { expected: usize, actual: usize },
+    HeaderChecksum { expected: u8, actual: u8 },
+    GlobalChecksum { expected: u16, actual: u16 },
+    UnsupportedCartridgeType(u8),
+    UnsupportedRomSize(u8),
+    UnsupportedRamSize(u8),
+}
+
+impl fmt::Display for CartridgeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CartridgeError::Io(e) => write!(f, "couldn't read ROM: {}", e),
+            CartridgeError::Truncated { expected, actual } =>
+                write!(f, "ROM is truncated, expected {} bytes but found {}", expected, actual),
+            CartridgeError::HeaderChecksum { expected, actual } =>
+                write!(f, "header checksum mismatch, header says 0x{:02X} but computed 0x{:02X}", expected, actual),
+            CartridgeError::GlobalChecksum { expected, actual } =>
+                write!(f, "global checksum mismatch, header says 0x{:04X} but computed 0x{:04X}", expected, actual),
+            CartridgeError::UnsupportedCartridgeType(byte) => match cartridge_type_name(*byte) {
+                Some(name) => write!(f, "unsupported mapper {} (cartridge type 0x{:02X})", name, byte),
+                None => write!(f, "unknown cartridge type 0x{:02X}", byte),
+            },
+            CartridgeError::UnsupportedRomSize(byte) =>
+                write!(f, "unsupported ROM size code 0x{:02X}", byte),
+            CartridgeError::UnsupportedRamSize(byte) =>
+                write!(f, "unsupported RAM size code 0x{:02X}", byte),
+        }
+    }
+}
+
+impl std::error::Error for CartridgeError {}
+
+impl From<io::Error> for CartridgeError {
+    fn from(e: io::Error) -> Self {
+        CartridgeError::Io(e)
+    }
+}
+
+// Names of cartridge types from the header, None if the byte isn't assigned
+pub fn cartridge_type_name(cartridge_type: u8) -> Option<&'static str> {
+    match cartridge_type {
+        0x00 => Some("ROM ONLY"),
+        0x01 => Some("MBC1"),
+        0x02 => Some("MBC1+RAM"),
+        0x03 => Some("MBC1+RAM+BATTERY"),
+        0x05 => Some("MBC2"),
+        0x06 => Some("MBC2+BATTERY"),
+        0x08 => Some("ROM+RAM"),
+        0x09 => Some("ROM+RAM+BATTERY"),
+        0x0B => Some("MMM01"),
+        0x0C => Some("MMM01+RAM"),
+        0x0D => Some("MMM01+RAM+BATTERY"),
+        0x0F => Some("MBC3+TIMER+BATTERY"),
+        0x10 => Some("MBC3+TIMER+RAM+BATTERY"),
+        0x11 => Some("MBC3"),
+        0x12 => Some("MBC3+RAM"),
+        0x13 => Some("MBC3+RAM+BATTERY"),
+        0x19 => Some("MBC5"),
+        0x1A => Some("MBC5+RAM"),
+        0x1B => Some("MBC5+RAM+BATTERY"),
+        0x1C => Some("MBC5+RUMBLE"),
+        0x1D => Some("MBC5+RUMBLE+RAM"),
+        0x1E => Some("MBC5+RUMBLE+RAM+BATTERY"),
+        0x20 => Some("MBC6"),
+        0x22 => Some("MBC7+SENSOR+RUMBLE+RAM+BATTERY"),
+        0xFC => Some("POCKET CAMERA"),
+        0xFD => Some("BANDAI TAMA5"),
+        0xFE => Some("HuC3"),
+        0xFF => Some("HuC1+RAM+BATTERY"),
+        _    => None,
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum CGBFlag {
+    DMG,      // no CGB features
+    Enhanced, // works on DMG, uses CGB features when available
+    CGBOnly,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Destination {
+    Japanese, Overseas,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Licensee {
+    Old(u8),
+    New(String),
+}
+
+pub struct CartridgeHeader {
+    pub title: String,
+    pub cgb_flag: CGBFlag,
+    pub sgb_flag: bool,
+    pub cartridge_type: u8,
+    pub rom_size: usize, // bytes
+    pub ram_size: usize, // bytes
+    pub destination: Destination,
+    pub licensee: Licensee,
+    pub header_checksum: u8,
+    pub global_checksum: u16,
+}
+
+impl CartridgeHeader {
+    // Parse header fields without validating checksums, rom must hold the whole header
+    pub fn parse(rom: &[u8]) -> Result<CartridgeHeader, CartridgeError> {
+        if rom.len() < HEADER_END {
+            return Err(CartridgeError::Truncated { expected: HEADER_END, actual: rom.len() });
+        }
+
+        let cgb_flag = match rom[CGB_FLAG_ADDRESS] {
+            0x80 => CGBFlag::Enhanced,
+            0xC0 => CGBFlag::CGBOnly,
+            _    => CGBFlag::DMG,
+        };
+
+        // the last title byte is the CGB flag on CGB aware cartridges
+        let title_end = if cgb_flag == CGBFlag::DMG { TITLE_END } else { CGB_FLAG_ADDRESS };
+        let title = rom[TITLE_START..title_end]
+            .iter()
+            .take_while(|&&byte| byte != 0)
+            .map(|&byte| byte as char)
+            .collect::<String>();
+
+        let rom_size = match rom[ROM_SIZE_ADDRESS] {
+            code @ 0x00..=0x08 => 0x8000 << code,
+            code => return Err(CartridgeError::UnsupportedRomSize(code)),
+        };
+
+        let ram_size = match rom[RAM_SIZE_ADDRESS] {
+            0x00 => 0,
+            0x01 => 0x800, // unofficial 2 KiB
+            0x02 => 0x2000,
+            0x03 => 0x8000,
+            0x04 => 0x20000,
+            0x05 => 0x10000,
+            code => return Err(CartridgeError::UnsupportedRamSize(code)),
+        };
+
+        let destination = match rom[DESTINATION_ADDRESS] {
+            0x00 => Destination::Japanese,
+            _    => Destination::Overseas,
+        };
+
+        let licensee = match rom[OLD_LICENSEE_ADDRESS] {
+            USE_NEW_LICENSEE => Licensee::New(
+                rom[NEW_LICENSEE_ADDRESS..NEW_LICENSEE_ADDRESS + 2]
+                    .iter()
+                    .map(|&byte| byte as char)
+                    .collect()
+            ),
+            code => Licensee::Old(code),
+        };
+
+        Ok(CartridgeHeader {
+            title,
+            cgb_flag,
+            sgb_flag: rom[SGB_FLAG_ADDRESS] == 0x03,
+            cartridge_type: rom[CARTRIDGE_TYPE_ADDRESS],
+            rom_size,
+            ram_size,
+            destination,
+            licensee,
+            header_checksum: rom[HEADER_CHECKSUM_ADDRESS],
+            global_checksum: (rom[GLOBAL_CHECKSUM_ADDRESS] as u16) << 8 | rom[GLOBAL_CHECKSUM_ADDRESS + 1] as u16,
+        })
+    }
+
+    // battery backed RAM or RTC keeps its contents without power
+    pub fn has_battery(&self) -> bool {
+        matches!(self.cartridge_type, 0x03 | 0x06 | 0x09 | 0x0D | 0x0F | 0x10 | 0x13 | 0x1B | 0x1E | 0x22 | 0xFF)
+    }
+}
+
+// checksum over 0x0134 - 0x014C, checked by the boot ROM
+pub fn compute_header_checksum(rom: &[u8]) -> u8 {
+    rom[TITLE_START..HEADER_CHECKSUM_ADDRESS]
+        .iter()
+        .fold(0u8, |checksum, &byte| checksum.wrapping_sub(byte).wrapping_sub(1))
+}
+
+// sum of every byte in the ROM except the global checksum itself
+pub fn compute_global_checksum(rom: &[u8]) -> u16 {
+    rom.iter()
+        .enumerate()
+        .filter(|&(i, _)| i != GLOBAL_CHECKSUM_ADDRESS && i != GLOBAL_CHECKSUM_ADDRESS + 1)
+        .fold(0u16, |checksum, (_, &byte)| checksum.wrapping_add(byte as u16))
+}
+
+// write both checksums so a test ROM passes header validation
+#[cfg(test)]
+pub(crate) fn fix_checksums(rom: &mut [u8]) {
+    rom[HEADER_CHECKSUM_ADDRESS] = compute_header_checksum(rom);
+    let global_checksum = compute_global_checksum(rom);
+    rom[GLOBAL_CHECKSUM_ADDRESS] = (global_checksum >> 8) as u8;
+    rom[GLOBAL_CHECKSUM_ADDRESS + 1] = (global_checksum & 0xFF) as u8;
+}
+
+// 32 KiB ROM only cartridge running program from the entry point
+#[cfg(test)]
+pub(crate) fn rom_with_program(program: &[u8]) -> Vec<u8> {
+    let mut rom = vec![0; 0x8000];
+    rom[0x100..0x100 + program.len()].copy_from_slice(program);
+    fix_checksums(&mut rom);
+    rom
+}
+
+// Hardware on the cartridge that maps ROM and RAM banks into the address space
+pub enum MemoryBankController {
+    RomOnly,
+    MBC1(MBC1),
+    MBC2(MBC2),
+    MBC3(MBC3),
+    MBC5(MBC5),
+}
+
+pub struct Cartridge {
+    pub header: CartridgeHeader,
+    pub rom: Vec<u8>,
+    pub ram: Vec<u8>,
+    pub mbc: MemoryBankController,
+    pub dirty: bool, // battery backed state changed since the last save
+}
+
+impl Cartridge {
+    // 32 KiB of zeroed ROM, stands in when no cartridge is inserted
+    pub fn empty() -> Cartridge {
+        let rom = vec![0; 0x8000];
+        Cartridge {
+            header: CartridgeHeader::parse(&rom).expect("An empty ROM has a valid header"),
+            rom,
+            ram: Vec::new(),
+            mbc: MemoryBankController::RomOnly,
+            dirty: false,
+        }
+    }
+
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Cartridge, CartridgeError> {
+        let bytes = fs::read(path)?;
+        Cartridge::from_bytes(&bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Cartridge, CartridgeError> {
+        let header = CartridgeHeader::parse(bytes)?;
+
+        if bytes.len() < header.rom_size {
+            return Err(CartridgeError::Truncated { expected: header.rom_size, actual: bytes.len() });
+        }
+        let rom = bytes[..header.rom_size].to_vec();
+
+        let header_checksum = compute_header_checksum(&rom);
+        if header_checksum != header.header_checksum {
+            return Err(CartridgeError::HeaderChecksum { expected: header.header_checksum, actual: header_checksum });
+        }
+
+        let global_checksum = compute_global_checksum(&rom);
+        if global_checksum != header.global_checksum {
+            return Err(CartridgeError::GlobalChecksum { expected: header.global_checksum, actual: global_checksum });
+        }
+
+        let mbc = match header.cartridge_type {
+            0x00 | 0x08 | 0x09 => MemoryBankController::RomOnly,
+            0x01..=0x03 => MemoryBankController::MBC1(MBC1::new(&rom, header.ram_size)),
+            0x05 | 0x06 => MemoryBankController::MBC2(MBC2::new(&rom)),
+            // 0x0F and 0x10 have a real time clock
+            0x0F..=0x13 => MemoryBankController::MBC3(MBC3::new(&rom, header.cartridge_type <= 0x10)),
+            // 0x1C - 0x1E have a rumble motor
+            0x19..=0x1E => MemoryBankController::MBC5(MBC5::new(&rom, header.cartridge_type >= 0x1C)),
+            byte => return Err(CartridgeError::UnsupportedCartridgeType(byte)),
+        };
+
+        // MBC2 RAM is built in, the header doesn't report it
+        let ram_size = match mbc {
+            MemoryBankController::MBC2(_) => mbc2::MBC2_RAM_SIZE,
+            _ => header.ram_size,
+        };
+
+        Ok(Cartridge {
+            ram: vec![0; ram_size],
+            header,
+            rom,
+            mbc,
+            dirty: false,
+        })
+    }
+
+    // 0x0000 - 0x7FFF
+    pub fn read_rom(&self, address: u16) -> u8 {
+        match &self.mbc {
+            MemoryBankController::RomOnly => self.rom.get(address as usize).copied().unwrap_or(0xFF),
+            MemoryBankController::MBC1(mbc) => mbc.read_rom(&self.rom, address),
+            MemoryBankController::MBC2(mbc) => mbc.read_rom(&self.rom, address),
+            MemoryBankController::MBC3(mbc) => mbc.read_rom(&self.rom, address),
+            MemoryBankController::MBC5(mbc) => mbc.read_rom(&self.rom, address),
+        }
+    }
+
+    // writes to ROM go to the memory bank controller, if there is one
+    pub fn write_rom(&mut self, address: u16, value: u8) {
+        match &mut self.mbc {
+            MemoryBankController::RomOnly => {},
+            MemoryBankController::MBC1(mbc) => mbc.write_rom(address, value),
+            MemoryBankController::MBC2(mbc) => mbc.write_rom(address, value),
+            MemoryBankController::MBC3(mbc) => mbc.write_rom(address, value),
+            MemoryBankController::MBC5(mbc) => mbc.write_rom(address, value),
+        }
+    }
+
+    // 0xA000 - 0xBFFF, reads 0xFF without RAM
+    pub fn read_ram(&self, address: u16) -> u8 {
+        match &self.mbc {
+            MemoryBankController::RomOnly => self.ram.get((address - 0xA000) as usize).copied().unwrap_or(0xFF),
+            MemoryBankController::MBC1(mbc) => mbc.read_ram(&self.ram, address),
+            MemoryBankController::MBC2(mbc) => mbc.read_ram(&self.ram, address),
+            MemoryBankController::MBC3(mbc) => mbc.read_ram(&self.ram, address),
+            MemoryBankController::MBC5(mbc) => mbc.read_ram(&self.ram, address),
+        }
+    }
+
+    pub fn write_ram(&mut self, address: u16, value: u8) {
+        // only writes that reached RAM or the RTC need saving
+        let stored = match &mut self.mbc {
+            MemoryBankController::RomOnly => match self.ram.get_mut((address - 0xA000) as usize) {
+                Some(byte) => {
+                    *byte = value;
+                    true
+                },
+                None => false,
+            },
+            MemoryBankController::MBC1(mbc) => mbc.write_ram(&mut self.ram, address, value),
+            MemoryBankController::MBC2(mbc) => mbc.write_ram(&mut self.ram, address, value),
+            MemoryBankController::MBC3(mbc) => mbc.write_ram(&mut self.ram, address, value),
+            MemoryBankController::MBC5(mbc) => mbc.write_ram(&mut self.ram, address, value),
+        };
+        if stored && self.header.has_battery() {
+            self.dirty = true;
+        }
+    }
+
+    // save file sits next to the ROM, game.gb -> game.sav
+    pub fn save_path<P: AsRef<Path>>(rom_path: P) -> PathBuf {
+        rom_path.as_ref().with_extension("sav")
+    }
+
+    fn rtc(&self) -> Option<&RealTimeClock> {
+        match &self.mbc {
+            MemoryBankController::MBC3(mbc) => mbc.rtc.as_ref(),
+            _ => None,
+        }
+    }
+
+    // RAM contents followed by the RTC trailer on MBC3 carts with a clock
+    pub fn save_data(&self) -> Vec<u8> {
+        let mut data = self.ram.clone();
+        if let Some(rtc) = self.rtc() {
+            data.extend_from_slice(&rtc.to_save_bytes());
+        }
+        data
+    }
+
+    // short or oversized saves load as much RAM as fits, a missing or bad trailer keeps the current RTC
+    pub fn load_save_data(&mut self, data: &[u8]) {
+        let ram_len = self.ram.len().min(data.len());
+        self.ram[..ram_len].copy_from_slice(&data[..ram_len]);
+
+        if let MemoryBankController::MBC3(MBC3 { rtc: Some(rtc), .. }) = &mut self.mbc {
+            if data.len() >= self.ram.len() + RTC_SAVE_SIZE_SHORT {
+                if let Some(loaded) = RealTimeClock::from_save_bytes(&data[self.ram.len()..]) {
+                    *rtc = loaded;
+                }
+            }
+        }
+        self.dirty = false;
+    }
+
+    // Write battery backed state, does nothing for cartridges without a battery
+    pub fn save<P: AsRef<Path>>(&mut self, path: P) -> Result<(), CartridgeError> {
+        if !self.header.has_battery() {
+            return Ok(());
+        }
+        fs::write(path, self.save_data())?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    // Load battery backed state, a missing save file leaves RAM cleared
+    pub fn load_save<P: AsRef<Path>>(&mut self, path: P) -> Result<(), CartridgeError> {
+        if !self.header.has_battery() {
+            return Ok(());
+        }
+        match fs::read(path) {
+            Ok(data) => self.load_save_data(&data),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {},
+            Err(e) => return Err(e.into()),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test_cartridge;
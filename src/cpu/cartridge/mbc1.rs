@@ -0,0 +1,109 @@
+// MBC1 memory bank controller
+// 0x0000 - 0x1FFF RAM enable, 0x0A in the low nibble enables
+// 0x2000 - 0x3FFF BANK1, lower 5 bits of the ROM bank, 0 maps to 1
+// 0x4000 - 0x5FFF BANK2, RAM bank or upper 2 bits of the ROM bank
+// 0x6000 - 0x7FFF mode, 1 lets BANK2 apply to 0x0000 - 0x3FFF and RAM
+pub struct MBC1 {
+    ram_enabled: bool,
+    bank1: u8,
+    bank2: u8,
+    mode: bool,
+    multicart: bool, // MBC1M wires only 4 bits of BANK1
+    rom_banks: usize,
+    ram_banks: usize,
+}
+
+const ROM_BANK_SIZE: usize = 0x4000;
+const RAM_BANK_SIZE: usize = 0x2000;
+
+// Nintendo logo, repeated at the start of every game in a multicart
+const LOGO_START: usize = 0x0104;
+const LOGO_END:   usize = 0x0134;
+
+impl MBC1 {
+    pub fn new(rom: &[u8], ram_size: usize) -> MBC1 {
+        MBC1 {
+            ram_enabled: false,
+            bank1: 1,
+            bank2: 0,
+            mode: false,
+            multicart: MBC1::is_multicart(rom),
+            rom_banks: (rom.len() / ROM_BANK_SIZE).max(1),
+            ram_banks: ram_size.div_ceil(RAM_BANK_SIZE),
+        }
+    }
+
+    // MBC1M carts are 1 MiB with a second game header at bank 0x10
+    fn is_multicart(rom: &[u8]) -> bool {
+        let second_logo = 0x10 * ROM_BANK_SIZE + LOGO_START;
+        rom.len() == 0x100000
+            && rom[LOGO_START..LOGO_END] == rom[second_logo..second_logo + (LOGO_END - LOGO_START)]
+    }
+
+    fn bank2_shift(&self) -> u8 {
+        if self.multicart { 4 } else { 5 }
+    }
+
+    fn low_bank(&self) -> usize {
+        if self.mode {
+            ((self.bank2 as usize) << self.bank2_shift()) % self.rom_banks
+        } else {
+            0
+        }
+    }
+
+    fn high_bank(&self) -> usize {
+        let bank1 = if self.multicart { self.bank1 & 0x0F } else { self.bank1 };
+        (((self.bank2 as usize) << self.bank2_shift()) | bank1 as usize) % self.rom_banks
+    }
+
+    fn ram_bank(&self) -> usize {
+        if self.mode && self.ram_banks > 0 {
+            self.bank2 as usize % self.ram_banks
+        } else {
+            0
+        }
+    }
+
+    pub fn read_rom(&self, rom: &[u8], address: u16) -> u8 {
+        let bank = match address {
+            0x0000..=0x3FFF => self.low_bank(),
+            _ => self.high_bank(),
+        };
+        let offset = bank * ROM_BANK_SIZE + (address as usize & (ROM_BANK_SIZE - 1));
+        rom.get(offset).copied().unwrap_or(0xFF)
+    }
+
+    pub fn write_rom(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => self.ram_enabled = value & 0x0F == 0x0A,
+            0x2000..=0x3FFF => {
+                // bank 0 can't be selected in BANK1, banks 0x20/0x40/0x60 map one higher
+                self.bank1 = value & 0x1F;
+                if self.bank1 == 0 {
+                    self.bank1 = 1;
+                }
+            },
+            0x4000..=0x5FFF => self.bank2 = value & 0x03,
+            _ => self.mode = value & 0x01 != 0,
+        }
+    }
+
+    pub fn read_ram(&self, ram: &[u8], address: u16) -> u8 {
+        if !self.ram_enabled || ram.is_empty() {
+            return 0xFF;
+        }
+        let offset = self.ram_bank() * RAM_BANK_SIZE + (address - 0xA000) as usize;
+        ram.get(offset % ram.len()).copied().unwrap_or(0xFF)
+    }
+
+    pub fn write_ram(&self, ram: &mut [u8], address: u16, value: u8) -> bool {
+        if !self.ram_enabled || ram.is_empty() {
+            return false;
+        }
+        let offset = self.ram_bank() * RAM_BANK_SIZE + (address - 0xA000) as usize;
+        let len = ram.len();
+        ram[offset % len] = value;
+        true
+    }
+}
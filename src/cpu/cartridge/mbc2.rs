@@ -0,0 +1,60 @@
+// MBC2 memory bank controller with 512 x 4 bit built in RAM
+// 0x0000 - 0x3FFF address bit 8 clear: RAM enable, 0x0A in the low nibble enables
+//                 address bit 8 set: 4 bit ROM bank, 0 maps to 1
+// 0xA000 - 0xBFFF built in RAM, echoed every 512 bytes
+pub struct MBC2 {
+    ram_enabled: bool,
+    rom_bank: u8,
+    rom_banks: usize,
+}
+
+const ROM_BANK_SIZE: usize = 0x4000;
+pub const MBC2_RAM_SIZE: usize = 0x200;
+
+impl MBC2 {
+    pub fn new(rom: &[u8]) -> MBC2 {
+        MBC2 {
+            ram_enabled: false,
+            rom_bank: 1,
+            rom_banks: (rom.len() / ROM_BANK_SIZE).max(1),
+        }
+    }
+
+    pub fn read_rom(&self, rom: &[u8], address: u16) -> u8 {
+        let bank = match address {
+            0x0000..=0x3FFF => 0,
+            _ => self.rom_bank as usize % self.rom_banks,
+        };
+        let offset = bank * ROM_BANK_SIZE + (address as usize & (ROM_BANK_SIZE - 1));
+        rom.get(offset).copied().unwrap_or(0xFF)
+    }
+
+    pub fn write_rom(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x3FFF if address & 0x0100 == 0 => self.ram_enabled = value & 0x0F == 0x0A,
+            0x0000..=0x3FFF => {
+                self.rom_bank = value & 0x0F;
+                if self.rom_bank == 0 {
+                    self.rom_bank = 1;
+                }
+            },
+            _ => {},
+        }
+    }
+
+    // only the low nibble exists, the upper nibble reads as 1
+    pub fn read_ram(&self, ram: &[u8], address: u16) -> u8 {
+        if !self.ram_enabled || ram.is_empty() {
+            return 0xFF;
+        }
+        ram[(address - 0xA000) as usize % MBC2_RAM_SIZE] | 0xF0
+    }
+
+    pub fn write_ram(&self, ram: &mut [u8], address: u16, value: u8) -> bool {
+        if !self.ram_enabled || ram.is_empty() {
+            return false;
+        }
+        ram[(address - 0xA000) as usize % MBC2_RAM_SIZE] = value & 0x0F;
+        true
+    }
+}
@@ -0,0 +1,91 @@
+use super::real_time_clock::{unix_time, RealTimeClock};
+
+// MBC3 memory bank controller
+// 0x0000 - 0x1FFF RAM and RTC enable, 0x0A in the low nibble enables
+// 0x2000 - 0x3FFF 7 bit ROM bank, 0 maps to 1
+// 0x4000 - 0x5FFF RAM bank 0x00 - 0x03 or RTC register 0x08 - 0x0C
+// 0x6000 - 0x7FFF writing 0x00 then 0x01 latches the RTC
+pub struct MBC3 {
+    ram_enabled: bool,
+    rom_bank: u8,
+    ram_bank: u8, // RTC register when 0x08 or above
+    latch_armed: bool, // last latch write was 0x00
+    rom_banks: usize,
+    pub rtc: Option<RealTimeClock>,
+}
+
+const ROM_BANK_SIZE: usize = 0x4000;
+const RAM_BANK_SIZE: usize = 0x2000;
+
+impl MBC3 {
+    pub fn new(rom: &[u8], has_rtc: bool) -> MBC3 {
+        MBC3 {
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            latch_armed: false,
+            rom_banks: (rom.len() / ROM_BANK_SIZE).max(1),
+            rtc: if has_rtc { Some(RealTimeClock::new(unix_time())) } else { None },
+        }
+    }
+
+    pub fn read_rom(&self, rom: &[u8], address: u16) -> u8 {
+        let bank = match address {
+            0x0000..=0x3FFF => 0,
+            _ => self.rom_bank as usize % self.rom_banks,
+        };
+        let offset = bank * ROM_BANK_SIZE + (address as usize & (ROM_BANK_SIZE - 1));
+        rom.get(offset).copied().unwrap_or(0xFF)
+    }
+
+    pub fn write_rom(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => self.ram_enabled = value & 0x0F == 0x0A,
+            0x2000..=0x3FFF => {
+                self.rom_bank = value & 0x7F;
+                if self.rom_bank == 0 {
+                    self.rom_bank = 1;
+                }
+            },
+            0x4000..=0x5FFF => self.ram_bank = value & 0x0F,
+            _ => {
+                if self.latch_armed && value == 0x01 {
+                    if let Some(rtc) = &mut self.rtc {
+                        rtc.latch(unix_time());
+                    }
+                }
+                self.latch_armed = value == 0x00;
+            },
+        }
+    }
+
+    pub fn read_ram(&self, ram: &[u8], address: u16) -> u8 {
+        if !self.ram_enabled {
+            return 0xFF;
+        }
+        match (self.ram_bank, &self.rtc) {
+            (0x00..=0x03, _) if !ram.is_empty() => {
+                let offset = self.ram_bank as usize * RAM_BANK_SIZE + (address - 0xA000) as usize;
+                ram[offset % ram.len()]
+            },
+            (0x08..=0x0C, Some(rtc)) => rtc.read(self.ram_bank),
+            _ => 0xFF,
+        }
+    }
+
+    pub fn write_ram(&mut self, ram: &mut [u8], address: u16, value: u8) -> bool {
+        if !self.ram_enabled {
+            return false;
+        }
+        match (self.ram_bank, &mut self.rtc) {
+            (0x00..=0x03, _) if !ram.is_empty() => {
+                let offset = self.ram_bank as usize * RAM_BANK_SIZE + (address - 0xA000) as usize;
+                let len = ram.len();
+                ram[offset % len] = value;
+            },
+            (0x08..=0x0C, Some(rtc)) => rtc.write(self.ram_bank, value, unix_time()),
+            _ => return false,
+        }
+        true
+    }
+}
@@ -0,0 +1,73 @@
+// MBC5 memory bank controller
+// 0x0000 - 0x1FFF RAM enable, 0x0A in the low nibble enables
+// 0x2000 - 0x2FFF lower 8 bits of the ROM bank, bank 0 is selectable
+// 0x3000 - 0x3FFF bit 8 of the ROM bank
+// 0x4000 - 0x5FFF RAM bank 0x00 - 0x0F, bit 3 drives the motor on rumble carts
+pub struct MBC5 {
+    ram_enabled: bool,
+    rom_bank: u16,
+    ram_bank: u8,
+    has_rumble: bool,
+    pub rumble: bool, // motor state, for the frontend to forward
+    rom_banks: usize,
+}
+
+const ROM_BANK_SIZE: usize = 0x4000;
+const RAM_BANK_SIZE: usize = 0x2000;
+
+impl MBC5 {
+    pub fn new(rom: &[u8], has_rumble: bool) -> MBC5 {
+        MBC5 {
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            has_rumble,
+            rumble: false,
+            rom_banks: (rom.len() / ROM_BANK_SIZE).max(1),
+        }
+    }
+
+    pub fn read_rom(&self, rom: &[u8], address: u16) -> u8 {
+        let bank = match address {
+            0x0000..=0x3FFF => 0,
+            _ => self.rom_bank as usize % self.rom_banks,
+        };
+        let offset = bank * ROM_BANK_SIZE + (address as usize & (ROM_BANK_SIZE - 1));
+        rom.get(offset).copied().unwrap_or(0xFF)
+    }
+
+    pub fn write_rom(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x1FFF => self.ram_enabled = value & 0x0F == 0x0A,
+            0x2000..=0x2FFF => self.rom_bank = (self.rom_bank & 0x100) | value as u16,
+            0x3000..=0x3FFF => self.rom_bank = (self.rom_bank & 0xFF) | ((value as u16 & 0x01) << 8),
+            0x4000..=0x5FFF => {
+                if self.has_rumble {
+                    self.rumble = value & 0x08 != 0;
+                    self.ram_bank = value & 0x07;
+                } else {
+                    self.ram_bank = value & 0x0F;
+                }
+            },
+            _ => {},
+        }
+    }
+
+    pub fn read_ram(&self, ram: &[u8], address: u16) -> u8 {
+        if !self.ram_enabled || ram.is_empty() {
+            return 0xFF;
+        }
+        let offset = self.ram_bank as usize * RAM_BANK_SIZE + (address - 0xA000) as usize;
+        ram[offset % ram.len()]
+    }
+
+    pub fn write_ram(&self, ram: &mut [u8], address: u16, value: u8) -> bool {
+        if !self.ram_enabled || ram.is_empty() {
+            return false;
+        }
+        let offset = self.ram_bank as usize * RAM_BANK_SIZE + (address - 0xA000) as usize;
+        let len = ram.len();
+        ram[offset % len] = value;
+        true
+    }
+}
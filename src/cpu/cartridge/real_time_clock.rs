@@ -0,0 +1,155 @@
+// Seconds since the unix epoch from the host clock
+pub fn unix_time() -> u64 {
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            (js_sys::Date::now() / 1000.0) as u64
+        } else {
+            use std::time::{SystemTime, UNIX_EPOCH};
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or(0)
+        }
+    }
+}
+
+const DH_DAY_HIGH_BYTE_POSITION: u8 = 0;
+const DH_HALT_BYTE_POSITION:     u8 = 6;
+const DH_CARRY_BYTE_POSITION:    u8 = 7;
+
+// BGB/VBA save trailer: 5 current and 5 latched registers as 32 bit words, then a 64 bit timestamp
+pub const RTC_SAVE_SIZE: usize = 48;
+// older saves store a 32 bit timestamp
+pub const RTC_SAVE_SIZE_SHORT: usize = 44;
+
+// MBC3 real time clock, counters advance with host wall clock time
+// registers are selected by writing 0x08 - 0x0C to the MBC3 RAM bank register
+#[derive(Clone, PartialEq, Debug)]
+pub struct RealTimeClock {
+    pub seconds: u8,
+    pub minutes: u8,
+    pub hours: u8,
+    pub days: u16, // 9 bits
+    pub halted: bool,
+    pub day_carry: bool, // sticky until cleared by a write to DH
+    pub latched: [u8; 5], // S, M, H, DL, DH as seen by the CPU
+    pub last_update: u64, // unix time the counters were last advanced
+}
+
+impl RealTimeClock {
+    pub fn new(now: u64) -> RealTimeClock {
+        RealTimeClock {
+            seconds: 0,
+            minutes: 0,
+            hours: 0,
+            days: 0,
+            halted: false,
+            day_carry: false,
+            latched: [0; 5],
+            last_update: now,
+        }
+    }
+
+    // Advance counters by the wall clock time since the last update
+    pub fn update(&mut self, now: u64) {
+        let elapsed = now.saturating_sub(self.last_update);
+        self.last_update = now;
+        if !self.halted {
+            self.advance(elapsed);
+        }
+    }
+
+    fn advance(&mut self, seconds: u64) {
+        let total_seconds = self.seconds as u64 + seconds;
+        self.seconds = (total_seconds % 60) as u8;
+
+        let total_minutes = self.minutes as u64 + total_seconds / 60;
+        self.minutes = (total_minutes % 60) as u8;
+
+        let total_hours = self.hours as u64 + total_minutes / 60;
+        self.hours = (total_hours % 24) as u8;
+
+        // day counter overflows at 512 and sets the carry bit
+        let total_days = self.days as u64 + total_hours / 24;
+        if total_days > 0x1FF {
+            self.day_carry = true;
+        }
+        self.days = (total_days % 0x200) as u16;
+    }
+
+    pub fn registers(&self) -> [u8; 5] {
+        let dh = (((self.days >> 8) as u8) & 0b1) << DH_DAY_HIGH_BYTE_POSITION |
+                 (if self.halted    { 1 } else { 0 }) << DH_HALT_BYTE_POSITION |
+                 (if self.day_carry { 1 } else { 0 }) << DH_CARRY_BYTE_POSITION;
+        [self.seconds, self.minutes, self.hours, (self.days & 0xFF) as u8, dh]
+    }
+
+    // Copy the current counters into the registers the CPU reads
+    pub fn latch(&mut self, now: u64) {
+        self.update(now);
+        self.latched = self.registers();
+    }
+
+    // Serialize in the BGB/VBA trailer format, all fields little endian
+    pub fn to_save_bytes(&self) -> [u8; RTC_SAVE_SIZE] {
+        let mut bytes = [0; RTC_SAVE_SIZE];
+        let words = self.registers().into_iter().chain(self.latched);
+        for (i, word) in words.enumerate() {
+            bytes[i * 4..i * 4 + 4].copy_from_slice(&(word as u32).to_le_bytes());
+        }
+        bytes[40..48].copy_from_slice(&self.last_update.to_le_bytes());
+        bytes
+    }
+
+    // Accepts the 48 byte trailer or the older 44 byte one
+    pub fn from_save_bytes(bytes: &[u8]) -> Option<RealTimeClock> {
+        let word = |i: usize| u32::from_le_bytes(bytes[i * 4..i * 4 + 4].try_into().unwrap()) as u8;
+        let last_update = match bytes.len() {
+            RTC_SAVE_SIZE => u64::from_le_bytes(bytes[40..48].try_into().unwrap()),
+            RTC_SAVE_SIZE_SHORT => u32::from_le_bytes(bytes[40..44].try_into().unwrap()) as u64,
+            _ => return None,
+        };
+
+        let dh = word(4);
+        Some(RealTimeClock {
+            seconds: word(0) & 0x3F,
+            minutes: word(1) & 0x3F,
+            hours: word(2) & 0x1F,
+            days: ((dh >> DH_DAY_HIGH_BYTE_POSITION) & 0b1) as u16 * 0x100 + word(3) as u16,
+            halted: (dh >> DH_HALT_BYTE_POSITION) & 0b1 != 0,
+            day_carry: (dh >> DH_CARRY_BYTE_POSITION) & 0b1 != 0,
+            latched: [word(5), word(6), word(7), word(8), word(9)],
+            last_update,
+        })
+    }
+
+    // register is 0x08 - 0x0C, reads see the latched values
+    pub fn read(&self, register: u8) -> u8 {
+        let value = self.latched[(register - 0x08) as usize];
+        // unused bits read back as 1
+        match register {
+            0x08 | 0x09 => value | 0xC0,
+            0x0A        => value | 0xE0,
+            0x0B        => value,
+            _           => value | 0x3E,
+        }
+    }
+
+    pub fn write(&mut self, register: u8, value: u8, now: u64) {
+        // bring counters up to date so elapsed time isn't lost or applied twice
+        self.update(now);
+        match register {
+            0x08 => self.seconds = value & 0x3F,
+            0x09 => self.minutes = value & 0x3F,
+            0x0A => self.hours = value & 0x1F,
+            0x0B => self.days = (self.days & 0x100) | value as u16,
+            _ => {
+                self.days = (self.days & 0xFF) | (((value >> DH_DAY_HIGH_BYTE_POSITION) & 0b1) as u16) << 8;
+                self.halted = (value >> DH_HALT_BYTE_POSITION) & 0b1 != 0;
+                self.day_carry = (value >> DH_CARRY_BYTE_POSITION) & 0b1 != 0;
+            },
+        }
+        // writes are visible without latching again
+        self.latched[(register - 0x08) as usize] = self.registers()[(register - 0x08) as usize];
+    }
+}
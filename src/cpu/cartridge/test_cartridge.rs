@@ -0,0 +1,478 @@
+use super::*;
+use super::real_time_clock::RealTimeClock;
+
+// build a ROM image with a valid header for the given type and size codes
+fn build_rom(cartridge_type: u8, rom_size_code: u8, ram_size_code: u8) -> Vec<u8> {
+    let mut rom = vec![0; 0x8000 << rom_size_code];
+    rom[TITLE_START..TITLE_START + 7].copy_from_slice(b"TESTROM");
+    rom[CARTRIDGE_TYPE_ADDRESS] = cartridge_type;
+    rom[ROM_SIZE_ADDRESS] = rom_size_code;
+    rom[RAM_SIZE_ADDRESS] = ram_size_code;
+    rom[DESTINATION_ADDRESS] = 0x01;
+    rom[OLD_LICENSEE_ADDRESS] = USE_NEW_LICENSEE;
+    rom[NEW_LICENSEE_ADDRESS..NEW_LICENSEE_ADDRESS + 2].copy_from_slice(b"01");
+    fix_checksums(&mut rom);
+    rom
+}
+
+#[test]
+fn test_parse_header() {
+    let mut rom = build_rom(0x00, 0x00, 0x00);
+    rom[SGB_FLAG_ADDRESS] = 0x03;
+    rom[CGB_FLAG_ADDRESS] = 0x80;
+    fix_checksums(&mut rom);
+
+    let cartridge = Cartridge::from_bytes(&rom).unwrap();
+    let header = &cartridge.header;
+    assert_eq!(header.title, "TESTROM");
+    assert_eq!(header.cgb_flag, CGBFlag::Enhanced);
+    assert!(header.sgb_flag);
+    assert_eq!(header.cartridge_type, 0x00);
+    assert_eq!(header.rom_size, 0x8000);
+    assert_eq!(header.ram_size, 0);
+    assert_eq!(header.destination, Destination::Overseas);
+    assert_eq!(header.licensee, Licensee::New(String::from("01")));
+}
+
+#[test]
+fn test_old_licensee_and_full_title() {
+    let mut rom = build_rom(0x00, 0x00, 0x00);
+    rom[TITLE_START..TITLE_END].copy_from_slice(b"SIXTEEN CHAR TTL");
+    rom[OLD_LICENSEE_ADDRESS] = 0x01;
+    rom[DESTINATION_ADDRESS] = 0x00;
+    fix_checksums(&mut rom);
+
+    let header = CartridgeHeader::parse(&rom).unwrap();
+    assert_eq!(header.title, "SIXTEEN CHAR TTL");
+    assert_eq!(header.licensee, Licensee::Old(0x01));
+    assert_eq!(header.destination, Destination::Japanese);
+}
+
+#[test]
+fn test_truncated() {
+    let rom = build_rom(0x00, 0x00, 0x00);
+    assert!(matches!(
+        Cartridge::from_bytes(&rom[..0x100]),
+        Err(CartridgeError::Truncated { expected: 0x150, actual: 0x100 })
+    ));
+
+    // header claims 64 KiB
+    let mut rom = build_rom(0x00, 0x00, 0x00);
+    rom[ROM_SIZE_ADDRESS] = 0x01;
+    fix_checksums(&mut rom);
+    assert!(matches!(
+        Cartridge::from_bytes(&rom),
+        Err(CartridgeError::Truncated { expected: 0x10000, actual: 0x8000 })
+    ));
+}
+
+#[test]
+fn test_checksums() {
+    let mut rom = build_rom(0x00, 0x00, 0x00);
+    rom[HEADER_CHECKSUM_ADDRESS] ^= 0xFF;
+    assert!(matches!(Cartridge::from_bytes(&rom), Err(CartridgeError::HeaderChecksum { .. })));
+
+    let mut rom = build_rom(0x00, 0x00, 0x00);
+    rom[0x4000] = 0x12;
+    assert!(matches!(Cartridge::from_bytes(&rom), Err(CartridgeError::GlobalChecksum { .. })));
+}
+
+#[test]
+fn test_unsupported() {
+    let rom = build_rom(0xFE, 0x00, 0x00);
+    assert!(matches!(Cartridge::from_bytes(&rom), Err(CartridgeError::UnsupportedCartridgeType(0xFE))));
+
+    let mut rom = build_rom(0x00, 0x00, 0x00);
+    rom[ROM_SIZE_ADDRESS] = 0x52;
+    fix_checksums(&mut rom);
+    assert!(matches!(Cartridge::from_bytes(&rom), Err(CartridgeError::UnsupportedRomSize(0x52))));
+
+    let error = Cartridge::from_path("/nonexistent/rom.gb").err().unwrap();
+    assert!(matches!(error, CartridgeError::Io(_)));
+    assert!(error.to_string().starts_with("couldn't read ROM"));
+}
+
+#[test]
+fn test_rom_only_access() {
+    let mut rom = build_rom(0x00, 0x00, 0x00);
+    rom[0x7FFF] = 0xAB;
+    fix_checksums(&mut rom);
+    let mut cartridge = Cartridge::from_bytes(&rom).unwrap();
+    cartridge.write_rom(0x7FFF, 0x00);
+    assert_eq!(cartridge.read_rom(0x7FFF), 0xAB);
+
+    // no RAM on the cartridge
+    cartridge.write_ram(0xA000, 0x12);
+    assert_eq!(cartridge.read_ram(0xA000), 0xFF);
+}
+
+// write the bank number at the start of every ROM bank
+fn tag_banks(rom: &mut [u8]) {
+    for bank in 1..(rom.len() / 0x4000) {
+        rom[bank * 0x4000] = bank as u8;
+    }
+    fix_checksums(rom);
+}
+
+#[test]
+fn test_mbc1_rom_banking() {
+    // 2 MiB, 128 banks
+    let mut rom = build_rom(0x01, 0x06, 0x00);
+    tag_banks(&mut rom);
+    let mut cartridge = Cartridge::from_bytes(&rom).unwrap();
+
+    // bank 1 by default, 0 maps to 1
+    assert_eq!(cartridge.read_rom(0x4000), 1);
+    cartridge.write_rom(0x2000, 0x00);
+    assert_eq!(cartridge.read_rom(0x4000), 1);
+    cartridge.write_rom(0x2000, 0x1F);
+    assert_eq!(cartridge.read_rom(0x4000), 0x1F);
+
+    // BANK2 supplies the upper bits
+    cartridge.write_rom(0x4000, 0x02);
+    assert_eq!(cartridge.read_rom(0x4000), 0x5F);
+
+    // bank 0x40 can't be reached, 0x41 is selected instead
+    cartridge.write_rom(0x2000, 0x00);
+    assert_eq!(cartridge.read_rom(0x4000), 0x41);
+
+    // mode 1 maps BANK2 into 0x0000 - 0x3FFF as well
+    assert_eq!(cartridge.read_rom(0x0000), 0x00);
+    cartridge.write_rom(0x6000, 0x01);
+    assert_eq!(cartridge.read_rom(0x0000), 0x40);
+}
+
+#[test]
+fn test_mbc1_bank_wraps_rom_size() {
+    // 256 KiB, 16 banks
+    let mut rom = build_rom(0x01, 0x03, 0x00);
+    tag_banks(&mut rom);
+    let mut cartridge = Cartridge::from_bytes(&rom).unwrap();
+    cartridge.write_rom(0x2000, 0x12);
+    assert_eq!(cartridge.read_rom(0x4000), 0x02);
+}
+
+#[test]
+fn test_mbc1_ram() {
+    // 32 KiB RAM, 4 banks
+    let rom = build_rom(0x03, 0x00, 0x03);
+    let mut cartridge = Cartridge::from_bytes(&rom).unwrap();
+
+    // disabled RAM ignores writes and reads 0xFF
+    cartridge.write_ram(0xA000, 0x12);
+    assert_eq!(cartridge.read_ram(0xA000), 0xFF);
+    cartridge.write_rom(0x0000, 0x0A);
+    cartridge.write_ram(0xA000, 0x12);
+    assert_eq!(cartridge.read_ram(0xA000), 0x12);
+
+    // RAM banks only switch in mode 1
+    cartridge.write_rom(0x4000, 0x02);
+    assert_eq!(cartridge.read_ram(0xA000), 0x12);
+    cartridge.write_rom(0x6000, 0x01);
+    assert_eq!(cartridge.read_ram(0xA000), 0x00);
+    cartridge.write_ram(0xA000, 0x34);
+    assert_eq!(cartridge.ram[0x4000], 0x34);
+
+    cartridge.write_rom(0x0000, 0x00);
+    assert_eq!(cartridge.read_ram(0xA000), 0xFF);
+}
+
+#[test]
+fn test_mbc1_multicart() {
+    // 1 MiB with a second logo at bank 0x10
+    let mut rom = build_rom(0x01, 0x05, 0x00);
+    for i in 0x0104..0x0134 {
+        rom[i] = i as u8;
+        rom[0x40000 + i] = i as u8;
+    }
+    tag_banks(&mut rom);
+    let mut cartridge = Cartridge::from_bytes(&rom).unwrap();
+
+    // BANK2 is shifted by 4, only 4 bits of BANK1 are used
+    cartridge.write_rom(0x4000, 0x01);
+    cartridge.write_rom(0x2000, 0x12);
+    assert_eq!(cartridge.read_rom(0x4000), 0x12);
+    cartridge.write_rom(0x6000, 0x01);
+    assert_eq!(cartridge.read_rom(0x0000), 0x10);
+}
+
+#[test]
+fn test_mbc3_rom_banking() {
+    // 2 MiB, 128 banks
+    let mut rom = build_rom(0x11, 0x06, 0x00);
+    tag_banks(&mut rom);
+    let mut cartridge = Cartridge::from_bytes(&rom).unwrap();
+
+    assert_eq!(cartridge.read_rom(0x4000), 1);
+    cartridge.write_rom(0x2000, 0x00);
+    assert_eq!(cartridge.read_rom(0x4000), 1);
+
+    // all 7 bits select directly, no holes like MBC1
+    cartridge.write_rom(0x2000, 0x40);
+    assert_eq!(cartridge.read_rom(0x4000), 0x40);
+    cartridge.write_rom(0x2000, 0x7F);
+    assert_eq!(cartridge.read_rom(0x4000), 0x7F);
+    assert_eq!(cartridge.read_rom(0x0000), 0x00);
+}
+
+#[test]
+fn test_mbc3_ram_banking() {
+    let rom = build_rom(0x13, 0x00, 0x03);
+    let mut cartridge = Cartridge::from_bytes(&rom).unwrap();
+    cartridge.write_rom(0x0000, 0x0A);
+    for bank in 0..4 {
+        cartridge.write_rom(0x4000, bank);
+        cartridge.write_ram(0xA000, 0x10 + bank);
+    }
+    for bank in 0..4 {
+        cartridge.write_rom(0x4000, bank);
+        assert_eq!(cartridge.read_ram(0xA000), 0x10 + bank);
+    }
+
+    // no RTC on this cartridge
+    cartridge.write_rom(0x4000, 0x08);
+    assert_eq!(cartridge.read_ram(0xA000), 0xFF);
+}
+
+#[test]
+fn test_mbc3_rtc_registers() {
+    let rom = build_rom(0x10, 0x00, 0x02);
+    let mut cartridge = Cartridge::from_bytes(&rom).unwrap();
+    cartridge.write_rom(0x0000, 0x0A);
+
+    // halt the clock so wall time doesn't interfere, then set hours
+    cartridge.write_rom(0x4000, 0x0C);
+    cartridge.write_ram(0xA000, 0x40);
+    cartridge.write_rom(0x4000, 0x0A);
+    cartridge.write_ram(0xA000, 0x17);
+    assert_eq!(cartridge.read_ram(0xA000), 0x17 | 0xE0);
+
+    // latch needs 0x00 then 0x01
+    cartridge.write_rom(0x6000, 0x01);
+    cartridge.write_rom(0x6000, 0x00);
+    cartridge.write_rom(0x6000, 0x01);
+    assert_eq!(cartridge.read_ram(0xA000), 0x17 | 0xE0);
+    cartridge.write_rom(0x4000, 0x0C);
+    assert_eq!(cartridge.read_ram(0xA000) & 0x40, 0x40);
+}
+
+#[test]
+fn test_rtc_advances() {
+    let mut rtc = RealTimeClock::new(1000);
+    rtc.update(1000 + 59);
+    assert_eq!(rtc.registers(), [59, 0, 0, 0, 0]);
+
+    // carry through minutes, hours and days
+    rtc.update(1000 + 60 * 60 * 24 + 60);
+    assert_eq!(rtc.registers(), [0, 1, 0, 1, 0]);
+
+    // latched registers only change on latch
+    assert_eq!(rtc.read(0x08), 0xC0);
+    rtc.latch(1000 + 60 * 60 * 24 + 60);
+    assert_eq!(rtc.read(0x09), 0xC1);
+}
+
+#[test]
+fn test_rtc_halt_and_day_carry() {
+    let mut rtc = RealTimeClock::new(0);
+
+    // halted clock doesn't advance
+    rtc.write(0x0C, 0x40, 0);
+    rtc.update(100);
+    assert_eq!(rtc.seconds, 0);
+
+    // day 511 rolls over into the carry bit
+    rtc.write(0x0B, 0xFF, 100);
+    rtc.write(0x0C, 0x01, 100);
+    assert_eq!(rtc.days, 0x1FF);
+    rtc.update(100 + 60 * 60 * 24);
+    assert_eq!(rtc.days, 0);
+    assert!(rtc.day_carry);
+    assert_eq!(rtc.registers()[4], 0x80);
+
+    // carry stays set until cleared
+    rtc.update(100 + 60 * 60 * 24 * 2);
+    assert!(rtc.day_carry);
+    rtc.write(0x0C, 0x00, 100 + 60 * 60 * 24 * 2);
+    assert!(!rtc.day_carry);
+}
+
+#[test]
+fn test_rom_ram() {
+    let rom = build_rom(0x08, 0x00, 0x02);
+    let mut cartridge = Cartridge::from_bytes(&rom).unwrap();
+    cartridge.write_ram(0xBFFF, 0x12);
+    assert_eq!(cartridge.read_ram(0xBFFF), 0x12);
+}
+
+#[test]
+fn test_mbc2() {
+    // 256 KiB, 16 banks, RAM size in the header is 0
+    let mut rom = build_rom(0x06, 0x03, 0x00);
+    tag_banks(&mut rom);
+    let mut cartridge = Cartridge::from_bytes(&rom).unwrap();
+    assert_eq!(cartridge.ram.len(), 512);
+
+    // address bit 8 selects ROM bank
+    cartridge.write_rom(0x2100, 0x0F);
+    assert_eq!(cartridge.read_rom(0x4000), 0x0F);
+    cartridge.write_rom(0x0100, 0x00);
+    assert_eq!(cartridge.read_rom(0x4000), 0x01);
+
+    // address bit 8 clear enables RAM
+    cartridge.write_rom(0x2000, 0x0A);
+    cartridge.write_ram(0xA000, 0xAB);
+    assert_eq!(cartridge.read_ram(0xA000), 0xFB);
+
+    // RAM echoes every 512 bytes
+    assert_eq!(cartridge.read_ram(0xA200), 0xFB);
+    assert_eq!(cartridge.read_ram(0xBE00), 0xFB);
+}
+
+#[test]
+fn test_mbc5() {
+    // 8 MiB, 512 banks, 128 KiB RAM
+    let mut rom = build_rom(0x1B, 0x08, 0x04);
+    for bank in 0..512 {
+        rom[bank * 0x4000 + 1] = (bank >> 8) as u8;
+        rom[bank * 0x4000 + 2] = (bank & 0xFF) as u8;
+    }
+    fix_checksums(&mut rom);
+    let mut cartridge = Cartridge::from_bytes(&rom).unwrap();
+
+    // 9 bit bank number
+    cartridge.write_rom(0x2000, 0x23);
+    cartridge.write_rom(0x3000, 0x01);
+    assert_eq!(cartridge.read_rom(0x4001), 0x01);
+    assert_eq!(cartridge.read_rom(0x4002), 0x23);
+
+    // bank 0 can be mapped to 0x4000 - 0x7FFF
+    cartridge.write_rom(0x2000, 0x00);
+    cartridge.write_rom(0x3000, 0x00);
+    assert_eq!(cartridge.read_rom(0x4002), 0x00);
+
+    // 16 RAM banks
+    cartridge.write_rom(0x0000, 0x0A);
+    cartridge.write_rom(0x4000, 0x0F);
+    cartridge.write_ram(0xA000, 0x12);
+    assert_eq!(cartridge.ram[0xF * 0x2000], 0x12);
+}
+
+#[test]
+fn test_mbc5_rumble() {
+    let rom = build_rom(0x1E, 0x00, 0x03);
+    let mut cartridge = Cartridge::from_bytes(&rom).unwrap();
+
+    // bit 3 drives the motor instead of selecting RAM
+    cartridge.write_rom(0x0000, 0x0A);
+    cartridge.write_rom(0x4000, 0x09);
+    cartridge.write_ram(0xA000, 0x12);
+    assert_eq!(cartridge.ram[0x2000], 0x12);
+    match &cartridge.mbc {
+        MemoryBankController::MBC5(mbc) => assert!(mbc.rumble),
+        _ => panic!("Expected an MBC5"),
+    }
+}
+
+#[test]
+fn test_unsupported_mapper_message() {
+    let rom = build_rom(0x0B, 0x00, 0x00);
+    let error = Cartridge::from_bytes(&rom).err().unwrap();
+    assert_eq!(error.to_string(), "unsupported mapper MMM01 (cartridge type 0x0B)");
+
+    let rom = build_rom(0x42, 0x00, 0x00);
+    let error = Cartridge::from_bytes(&rom).err().unwrap();
+    assert_eq!(error.to_string(), "unknown cartridge type 0x42");
+}
+
+#[test]
+fn test_save_data_round_trip() {
+    let rom = build_rom(0x03, 0x00, 0x02);
+    let mut cartridge = Cartridge::from_bytes(&rom).unwrap();
+    assert!(cartridge.header.has_battery());
+    cartridge.write_rom(0x0000, 0x0A);
+    cartridge.write_ram(0xA123, 0x45);
+    assert!(cartridge.dirty);
+
+    let data = cartridge.save_data();
+    assert_eq!(data.len(), 0x2000);
+
+    let mut loaded = Cartridge::from_bytes(&rom).unwrap();
+    loaded.load_save_data(&data);
+    assert_eq!(loaded.ram[0x123], 0x45);
+    assert!(!loaded.dirty);
+}
+
+#[test]
+fn test_no_battery_isnt_dirty() {
+    let rom = build_rom(0x02, 0x00, 0x02);
+    let mut cartridge = Cartridge::from_bytes(&rom).unwrap();
+    cartridge.write_rom(0x0000, 0x0A);
+    cartridge.write_ram(0xA000, 0x45);
+    assert!(!cartridge.dirty);
+}
+
+#[test]
+fn test_disabled_ram_isnt_dirty() {
+    let rom = build_rom(0x03, 0x00, 0x02);
+    let mut cartridge = Cartridge::from_bytes(&rom).unwrap();
+    cartridge.write_ram(0xA000, 0x45);
+    assert!(!cartridge.dirty);
+    assert_eq!(cartridge.ram[0], 0x00);
+
+    // disabling RAM again drops later writes too
+    cartridge.write_rom(0x0000, 0x0A);
+    cartridge.write_rom(0x0000, 0x00);
+    cartridge.write_ram(0xA000, 0x45);
+    assert!(!cartridge.dirty);
+
+    // MBC3 with no RAM and no clock stores nothing either
+    let rom = build_rom(0x13, 0x00, 0x00);
+    let mut cartridge = Cartridge::from_bytes(&rom).unwrap();
+    cartridge.write_rom(0x0000, 0x0A);
+    cartridge.write_ram(0xA000, 0x45);
+    assert!(!cartridge.dirty);
+}
+
+#[test]
+fn test_mbc3_rtc_trailer() {
+    let rom = build_rom(0x10, 0x00, 0x03);
+    let mut cartridge = Cartridge::from_bytes(&rom).unwrap();
+    cartridge.ram[0] = 0x99;
+    if let MemoryBankController::MBC3(MBC3 { rtc: Some(rtc), .. }) = &mut cartridge.mbc {
+        *rtc = RealTimeClock::new(1_600_000_000);
+        rtc.write(0x08, 30, 1_600_000_000);
+        rtc.write(0x0B, 0x10, 1_600_000_000);
+        rtc.write(0x0C, 0x81, 1_600_000_000);
+    }
+
+    let data = cartridge.save_data();
+    assert_eq!(data.len(), 0x8000 + 48);
+    let trailer = &data[0x8000..];
+    assert_eq!(trailer[0..4], [30, 0, 0, 0]);
+    assert_eq!(trailer[12..16], [0x10, 0, 0, 0]);
+    assert_eq!(trailer[16..20], [0x81, 0, 0, 0]);
+    assert_eq!(trailer[40..48], 1_600_000_000u64.to_le_bytes());
+
+    let mut loaded = Cartridge::from_bytes(&rom).unwrap();
+    loaded.load_save_data(&data);
+    assert_eq!(loaded.ram[0], 0x99);
+    match &loaded.mbc {
+        MemoryBankController::MBC3(MBC3 { rtc: Some(rtc), .. }) => {
+            assert_eq!(rtc.seconds, 30);
+            assert_eq!(rtc.days, 0x110);
+            assert!(rtc.day_carry);
+            assert_eq!(rtc.last_update, 1_600_000_000);
+        },
+        _ => panic!("Expected an MBC3 with a clock"),
+    }
+}
+
+#[test]
+fn test_rtc_short_trailer() {
+    let mut bytes = RealTimeClock::new(1234).to_save_bytes().to_vec();
+    bytes.truncate(44);
+    let rtc = RealTimeClock::from_save_bytes(&bytes).unwrap();
+    assert_eq!(rtc.last_update, 1234);
+    assert!(RealTimeClock::from_save_bytes(&bytes[..40]).is_none());
+}
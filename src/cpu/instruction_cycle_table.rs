@@ -2,7 +2,7 @@
 // should be in a data structure not a match statement
 // generated from a table of opcodes with excel and vim macros
 // conditional instructions have multiple cycle counts, return the lesser here
-// cpu will add extra cycles during execution
+// cpu will add extra cycles during execution when the condition is taken
 
 pub fn get_cycle_count(instruction: u8, prefixed: bool) -> u8 {
     match (instruction, prefixed) {
@@ -267,6 +267,14 @@ pub fn get_cycle_count(instruction: u8, prefixed: bool) -> u8 {
         (0xCF, false) => 16,
         (0xDF, false) => 16,
         (0xEF, false) => 16,
+        (0xFF, false) => 16,
+
+        // prefixed instructions follow the same pattern across the whole table,
+        // counts include fetching the 0xCB prefix
+        // (HL) targets cost extra memory accesses, BIT only reads (HL) back
+        (0x40..=0x7F, true) if instruction & 0x07 == 0x06 => 12,
+        (_, true) if instruction & 0x07 == 0x06 => 16,
+        (_, true) => 8,
 
         _ => 0,
     }
@@ -43,6 +43,17 @@ pub enum JumpAddr {
     IMM16, HL, REL,
 }
 
+// Register operand of 0xCB prefixed instructions, HL is treated as address
+#[derive(Copy, Clone)]
+pub enum PrefixTarget {
+    B, C, D, E, H, L, HL, A,
+}
+
+#[derive(Copy, Clone)]
+pub enum BitPosition {
+    B0, B1, B2, B3, B4, B5, B6, B7,
+}
+
 pub enum Instruction {
     LD(LoadType),
     SUB(ArithmeticTarget),
@@ -71,6 +82,22 @@ pub enum Instruction {
     CCF,
     DAA,
     SCF,
+    RLCA,
+    RRCA,
+    RLA,
+    RRA,
+    // 0xCB prefixed
+    RLC(PrefixTarget),
+    RRC(PrefixTarget),
+    RL(PrefixTarget),
+    RR(PrefixTarget),
+    SLA(PrefixTarget),
+    SRA(PrefixTarget),
+    SWAP(PrefixTarget),
+    SRL(PrefixTarget),
+    BIT(BitPosition, PrefixTarget),
+    RES(BitPosition, PrefixTarget),
+    SET(BitPosition, PrefixTarget),
 }
 
 impl Instruction {
@@ -318,10 +345,10 @@ impl Instruction {
             0x29 => Some(Instruction::ADD16(ArithmeticTarget16::HL)),
             0x39 => Some(Instruction::ADD16(ArithmeticTarget16::SP)),
 
-            0x0B => Some(Instruction::ADD16(ArithmeticTarget16::BC)),
-            0x1B => Some(Instruction::ADD16(ArithmeticTarget16::DE)),
-            0x2B => Some(Instruction::ADD16(ArithmeticTarget16::HL)),
-            0x3B => Some(Instruction::ADD16(ArithmeticTarget16::SP)),
+            0x0B => Some(Instruction::DEC16(ArithmeticTarget16::BC)),
+            0x1B => Some(Instruction::DEC16(ArithmeticTarget16::DE)),
+            0x2B => Some(Instruction::DEC16(ArithmeticTarget16::HL)),
+            0x3B => Some(Instruction::DEC16(ArithmeticTarget16::SP)),
 
             0xE8 => Some(Instruction::ADD16(ArithmeticTarget16::SPIMM)),
 
@@ -338,8 +365,10 @@ impl Instruction {
             0xD2 => Some(Instruction::JP(ControlCondition::NC, JumpAddr::IMM16)),
             0xC3 => Some(Instruction::JP(ControlCondition::NONE, JumpAddr::IMM16)),
 
-            0xE9 => Some(Instruction::JP(ControlCondition::Z, JumpAddr::IMM16)),
-            0xCA => Some(Instruction::JP(ControlCondition::C, JumpAddr::IMM16)),
+            0xCA => Some(Instruction::JP(ControlCondition::Z, JumpAddr::IMM16)),
+            0xDA => Some(Instruction::JP(ControlCondition::C, JumpAddr::IMM16)),
+
+            0xE9 => Some(Instruction::JP(ControlCondition::NONE, JumpAddr::HL)),
 
             // calls
 
@@ -382,12 +411,59 @@ impl Instruction {
             0x2F => Some(Instruction::CPL),
             0x3F => Some(Instruction::CCF),
 
+            // rotates on A
+
+            0x07 => Some(Instruction::RLCA),
+            0x0F => Some(Instruction::RRCA),
+            0x17 => Some(Instruction::RLA),
+            0x1F => Some(Instruction::RRA),
+
+            // 0xCB is the prefix byte and 11 opcodes are unused by the SM83
+
             _    => None,
         }
     }
     fn from_byte_prefixed(byte: u8) -> Option<Instruction> {
+        // prefixed opcodes are regular: low 3 bits select the target,
+        // bits 3-5 select the bit for BIT/RES/SET
+        let target = match byte & 0x07 {
+            0x00 => PrefixTarget::B,
+            0x01 => PrefixTarget::C,
+            0x02 => PrefixTarget::D,
+            0x03 => PrefixTarget::E,
+            0x04 => PrefixTarget::H,
+            0x05 => PrefixTarget::L,
+            0x06 => PrefixTarget::HL,
+            _    => PrefixTarget::A,
+        };
+        let bit = match (byte >> 3) & 0x07 {
+            0x00 => BitPosition::B0,
+            0x01 => BitPosition::B1,
+            0x02 => BitPosition::B2,
+            0x03 => BitPosition::B3,
+            0x04 => BitPosition::B4,
+            0x05 => BitPosition::B5,
+            0x06 => BitPosition::B6,
+            _    => BitPosition::B7,
+        };
+
         match byte {
-            _ => None // TODO: Implement prefixed opcodes
+            // rotates and shifts
+
+            0x00..=0x07 => Some(Instruction::RLC(target)),
+            0x08..=0x0F => Some(Instruction::RRC(target)),
+            0x10..=0x17 => Some(Instruction::RL(target)),
+            0x18..=0x1F => Some(Instruction::RR(target)),
+            0x20..=0x27 => Some(Instruction::SLA(target)),
+            0x28..=0x2F => Some(Instruction::SRA(target)),
+            0x30..=0x37 => Some(Instruction::SWAP(target)),
+            0x38..=0x3F => Some(Instruction::SRL(target)),
+
+            // single bit operations
+
+            0x40..=0x7F => Some(Instruction::BIT(bit, target)),
+            0x80..=0xBF => Some(Instruction::RES(bit, target)),
+            0xC0..=0xFF => Some(Instruction::SET(bit, target)),
         }
     }
 }
@@ -0,0 +1,82 @@
+// Interrupt sources, listed from highest to lowest priority
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Interrupt {
+    VBlank, LCDStat, Timer, Serial, Joypad,
+}
+
+const INTERRUPT_PRIORITY: [Interrupt; 5] = [
+    Interrupt::VBlank,
+    Interrupt::LCDStat,
+    Interrupt::Timer,
+    Interrupt::Serial,
+    Interrupt::Joypad,
+];
+
+impl Interrupt {
+    // bit in IF and IE
+    pub fn mask(self) -> u8 {
+        match self {
+            Interrupt::VBlank  => 1 << 0,
+            Interrupt::LCDStat => 1 << 1,
+            Interrupt::Timer   => 1 << 2,
+            Interrupt::Serial  => 1 << 3,
+            Interrupt::Joypad  => 1 << 4,
+        }
+    }
+
+    // address the CPU jumps to when servicing the interrupt
+    pub fn vector(self) -> u16 {
+        match self {
+            Interrupt::VBlank  => 0x40,
+            Interrupt::LCDStat => 0x48,
+            Interrupt::Timer   => 0x50,
+            Interrupt::Serial  => 0x58,
+            Interrupt::Joypad  => 0x60,
+        }
+    }
+}
+
+// Interrupt Flag (IF) 0xFF0F and Interrupt Enable (IE) 0xFFFF
+pub struct InterruptController {
+    pub flags: u8,
+    pub enable: u8,
+}
+
+impl InterruptController {
+    pub fn new() -> InterruptController {
+        InterruptController {
+            flags: 0,
+            enable: 0,
+        }
+    }
+
+    pub fn request(&mut self, interrupt: Interrupt) {
+        self.flags |= interrupt.mask();
+    }
+
+    pub fn acknowledge(&mut self, interrupt: Interrupt) {
+        self.flags &= !interrupt.mask();
+    }
+
+    // requested and enabled interrupts, regardless of IME
+    pub fn pending(&self) -> u8 {
+        self.flags & self.enable & 0x1F
+    }
+
+    pub fn highest_pending(&self) -> Option<Interrupt> {
+        let pending = self.pending();
+        INTERRUPT_PRIORITY
+            .iter()
+            .copied()
+            .find(|interrupt| pending & interrupt.mask() != 0)
+    }
+
+    // upper 3 bits of IF are unused and read back as 1
+    pub fn read_flags(&self) -> u8 {
+        self.flags | 0xE0
+    }
+
+    pub fn write_flags(&mut self, value: u8) {
+        self.flags = value & 0x1F;
+    }
+}
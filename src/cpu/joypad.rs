@@ -0,0 +1,83 @@
+use super::{Interrupt, InterruptController};
+
+// Buttons held down, set by the frontend
+#[derive(Copy, Clone, Default, PartialEq, Debug)]
+pub struct Buttons {
+    pub right:  bool,
+    pub left:   bool,
+    pub up:     bool,
+    pub down:   bool,
+    pub a:      bool,
+    pub b:      bool,
+    pub select: bool,
+    pub start:  bool,
+}
+
+const SELECT_DPAD_BYTE_POSITION:    u8 = 4;
+const SELECT_BUTTONS_BYTE_POSITION: u8 = 5;
+
+// Joypad (P1) 0xFF00
+// bits 4 and 5 select the d-pad and buttons when 0, bits 0 - 3 read 0 for pressed keys
+//       bit 3        bit 2         bit 1    bit 0
+// dpad  down         up            left     right
+// btns  start        select        B        A
+pub struct Joypad {
+    pub buttons: Buttons,
+    select: u8, // bits 4 and 5 as last written
+}
+
+impl Joypad {
+    pub fn new() -> Joypad {
+        Joypad {
+            buttons: Buttons::default(),
+            select: 0x30,
+        }
+    }
+
+    pub fn read(&self) -> u8 {
+        0xC0 | self.select | self.lines()
+    }
+
+    pub fn write(&mut self, value: u8, interrupts: &mut InterruptController) {
+        let old_lines = self.lines();
+        self.select = value & 0x30;
+        self.request_on_press(old_lines, interrupts);
+    }
+
+    pub fn set_buttons(&mut self, buttons: Buttons, interrupts: &mut InterruptController) {
+        let old_lines = self.lines();
+        self.buttons = buttons;
+        self.request_on_press(old_lines, interrupts);
+    }
+
+    // a pressed key in a selected group, this is what wakes the CPU from STOP
+    pub fn any_line_low(&self) -> bool {
+        self.lines() != 0x0F
+    }
+
+    // low nibble of P1, selected groups pull their pressed keys low
+    fn lines(&self) -> u8 {
+        let mut pressed = 0;
+        if (self.select >> SELECT_DPAD_BYTE_POSITION) & 0b1 == 0 {
+            pressed |= Joypad::pack(self.buttons.down, self.buttons.up, self.buttons.left, self.buttons.right);
+        }
+        if (self.select >> SELECT_BUTTONS_BYTE_POSITION) & 0b1 == 0 {
+            pressed |= Joypad::pack(self.buttons.start, self.buttons.select, self.buttons.b, self.buttons.a);
+        }
+        !pressed & 0x0F
+    }
+
+    fn pack(bit3: bool, bit2: bool, bit1: bool, bit0: bool) -> u8 {
+        (bit3 as u8) << 3 | (bit2 as u8) << 2 | (bit1 as u8) << 1 | bit0 as u8
+    }
+
+    // any line going from high to low requests the joypad interrupt
+    fn request_on_press(&self, old_lines: u8, interrupts: &mut InterruptController) {
+        if old_lines & !self.lines() != 0 {
+            interrupts.request(Interrupt::Joypad);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_joypad;
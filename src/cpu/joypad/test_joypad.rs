@@ -0,0 +1,51 @@
+use super::*;
+
+#[test]
+fn test_nothing_selected() {
+    let mut joypad = Joypad::new();
+    let mut interrupts = InterruptController::new();
+    joypad.set_buttons(Buttons { a: true, right: true, ..Buttons::default() }, &mut interrupts);
+    assert_eq!(joypad.read(), 0xFF);
+    assert_eq!(interrupts.flags, 0);
+}
+
+#[test]
+fn test_select_groups() {
+    let mut joypad = Joypad::new();
+    let mut interrupts = InterruptController::new();
+    joypad.set_buttons(Buttons { start: true, down: true, left: true, ..Buttons::default() }, &mut interrupts);
+
+    joypad.write(0x20, &mut interrupts);
+    assert_eq!(joypad.read(), 0xE5);
+
+    joypad.write(0x10, &mut interrupts);
+    assert_eq!(joypad.read(), 0xD7);
+
+    // both groups selected combine
+    joypad.write(0x00, &mut interrupts);
+    assert_eq!(joypad.read(), 0xC5);
+}
+
+#[test]
+fn test_interrupt_on_press() {
+    let mut joypad = Joypad::new();
+    let mut interrupts = InterruptController::new();
+    joypad.write(0x10, &mut interrupts);
+    assert_eq!(interrupts.flags, 0);
+
+    joypad.set_buttons(Buttons { b: true, ..Buttons::default() }, &mut interrupts);
+    assert_eq!(interrupts.flags, Interrupt::Joypad.mask());
+    interrupts.acknowledge(Interrupt::Joypad);
+
+    // releasing doesn't interrupt
+    joypad.set_buttons(Buttons::default(), &mut interrupts);
+    assert_eq!(interrupts.flags, 0);
+
+    // pressing a key in the unselected group doesn't either
+    joypad.set_buttons(Buttons { up: true, ..Buttons::default() }, &mut interrupts);
+    assert_eq!(interrupts.flags, 0);
+
+    // selecting a group with a held key pulls a line low
+    joypad.write(0x20, &mut interrupts);
+    assert_eq!(interrupts.flags, Interrupt::Joypad.mask());
+}
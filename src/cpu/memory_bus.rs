@@ -0,0 +1,198 @@
+use super::Cartridge;
+use super::InterruptController;
+use super::Timer;
+use super::Joypad;
+use super::Serial;
+use super::PPU;
+use super::APU;
+
+// IO register values left behind by the DMG boot ROM
+const POST_BOOT_IO: [(u16, u8); 37] = [
+    (0xFF00, 0xCF), // P1
+    (0xFF01, 0x00), // SB
+    (0xFF02, 0x7E), // SC
+    (0xFF05, 0x00), // TIMA
+    (0xFF06, 0x00), // TMA
+    (0xFF07, 0xF8), // TAC
+    (0xFF0F, 0xE1), // IF
+    (0xFF10, 0x80), // NR10
+    (0xFF11, 0xBF), // NR11
+    (0xFF12, 0xF3), // NR12
+    (0xFF13, 0xFF), // NR13
+    (0xFF14, 0xBF), // NR14
+    (0xFF16, 0x3F), // NR21
+    (0xFF17, 0x00), // NR22
+    (0xFF18, 0xFF), // NR23
+    (0xFF19, 0xBF), // NR24
+    (0xFF1A, 0x7F), // NR30
+    (0xFF1B, 0xFF), // NR31
+    (0xFF1C, 0x9F), // NR32
+    (0xFF1D, 0xFF), // NR33
+    (0xFF1E, 0xBF), // NR34
+    (0xFF20, 0xFF), // NR41
+    (0xFF21, 0x00), // NR42
+    (0xFF22, 0x00), // NR43
+    (0xFF23, 0xBF), // NR44
+    (0xFF24, 0x77), // NR50
+    (0xFF25, 0xF3), // NR51
+    (0xFF26, 0xF1), // NR52
+    (0xFF40, 0x91), // LCDC
+    (0xFF41, 0x85), // STAT
+    (0xFF42, 0x00), // SCY
+    (0xFF43, 0x00), // SCX
+    (0xFF45, 0x00), // LYC
+    (0xFF47, 0xFC), // BGP
+    (0xFF4A, 0x00), // WY
+    (0xFF4B, 0x00), // WX
+    (0xFFFF, 0x00), // IE
+];
+
+// internal divider when the boot ROM hands over, DIV reads 0xAB
+const POST_BOOT_DIVIDER: u16 = 0xABCC;
+
+// Memory map
+// 0x0000 - 0x00FF boot ROM until 0xFF50 is written
+// 0x0000 - 0x7FFF cartridge ROM
+// 0x8000 - 0x9FFF video RAM
+// 0xA000 - 0xBFFF cartridge RAM
+// 0xC000 - 0xDFFF work RAM
+// 0xE000 - 0xFDFF echo of 0xC000 - 0xDDFF
+// 0xFE00 - 0xFE9F object attribute memory
+// 0xFEA0 - 0xFEFF unusable
+// 0xFF00 - 0xFF7F IO registers
+// 0xFF80 - 0xFFFE high RAM
+// 0xFFFF          interrupt enable
+pub struct MemoryBus {
+    pub cartridge: Cartridge,
+    pub wram: [u8; 0x2000],
+    pub io: [u8; 0x80], // registers not owned by another component
+    pub hram: [u8; 0x7F],
+    pub interrupts: InterruptController,
+    pub timer: Timer,
+    pub ppu: PPU, // also owns video RAM and OAM
+    pub joypad: Joypad,
+    pub serial: Serial,
+    pub apu: APU,
+    pub boot_rom: Option<[u8; 0x100]>,
+}
+
+impl MemoryBus {
+    pub fn new() -> MemoryBus {
+        MemoryBus {
+            cartridge: Cartridge::empty(),
+            wram: [0; 0x2000],
+            io: [0; 0x80],
+            hram: [0; 0x7F],
+            interrupts: InterruptController::new(),
+            timer: Timer::new(),
+            ppu: PPU::new(),
+            joypad: Joypad::new(),
+            serial: Serial::new(),
+            apu: APU::new(),
+            boot_rom: None,
+        }
+    }
+
+    // IO as the boot ROM leaves it, for starting straight at the cartridge entry point
+    pub fn init_post_boot(&mut self) {
+        self.boot_rom = None;
+        for &(address, value) in POST_BOOT_IO.iter() {
+            self.write_byte(address, value);
+        }
+        self.timer.set_divider(POST_BOOT_DIVIDER);
+    }
+
+    // Advance components clocked alongside the CPU
+    pub fn tick(&mut self, cycles: u8) {
+        self.timer.tick(cycles, &mut self.interrupts);
+        self.ppu.tick(cycles, &mut self.interrupts);
+        self.serial.tick(cycles, &mut self.interrupts);
+        self.apu.tick(cycles, self.timer.read_div());
+    }
+
+    pub fn read_byte(&self, address: u16) -> u8 {
+        match address {
+            0x0000..=0x00FF if self.boot_rom.is_some() =>
+                self.boot_rom.as_ref().unwrap()[address as usize],
+            0x0000..=0x7FFF => self.cartridge.read_rom(address),
+            0x8000..=0x9FFF => self.ppu.vram[(address - 0x8000) as usize],
+            0xA000..=0xBFFF => self.cartridge.read_ram(address),
+            0xC000..=0xDFFF => self.wram[(address - 0xC000) as usize],
+            0xE000..=0xFDFF => self.wram[(address - 0xE000) as usize],
+            0xFE00..=0xFE9F => self.ppu.oam[(address - 0xFE00) as usize],
+            0xFEA0..=0xFEFF => 0x00,
+            0xFF00..=0xFF7F => self.read_io(address),
+            0xFF80..=0xFFFE => self.hram[(address - 0xFF80) as usize],
+            0xFFFF          => self.interrupts.enable,
+        }
+    }
+
+    pub fn write_byte(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000..=0x7FFF => self.cartridge.write_rom(address, value),
+            0x8000..=0x9FFF => self.ppu.vram[(address - 0x8000) as usize] = value,
+            0xA000..=0xBFFF => self.cartridge.write_ram(address, value),
+            0xC000..=0xDFFF => self.wram[(address - 0xC000) as usize] = value,
+            0xE000..=0xFDFF => self.wram[(address - 0xE000) as usize] = value,
+            0xFE00..=0xFE9F => self.ppu.oam[(address - 0xFE00) as usize] = value,
+            0xFEA0..=0xFEFF => {},
+            0xFF00..=0xFF7F => self.write_io(address, value),
+            0xFF80..=0xFFFE => self.hram[(address - 0xFF80) as usize] = value,
+            0xFFFF          => self.interrupts.enable = value,
+        }
+    }
+
+    fn read_io(&self, address: u16) -> u8 {
+        match address {
+            0xFF00 => self.joypad.read(),
+            0xFF01 => self.serial.data,
+            0xFF02 => self.serial.read_control(),
+            0xFF04 => self.timer.read_div(),
+            0xFF05 => self.timer.tima,
+            0xFF06 => self.timer.tma,
+            0xFF07 => self.timer.read_tac(),
+            0xFF0F => self.interrupts.read_flags(),
+            0xFF10..=0xFF3F => self.apu.read_register(address),
+            0xFF40..=0xFF45 | 0xFF47..=0xFF4B => self.ppu.read_register(address),
+            0xFF50 => 0xFF,
+            _ => self.io[(address - 0xFF00) as usize],
+        }
+    }
+
+    fn write_io(&mut self, address: u16, value: u8) {
+        match address {
+            0xFF00 => self.joypad.write(value, &mut self.interrupts),
+            0xFF01 => self.serial.data = value,
+            0xFF02 => self.serial.write_control(value),
+            0xFF04 => self.timer.write_div(),
+            0xFF05 => self.timer.write_tima(value),
+            0xFF06 => self.timer.tma = value,
+            0xFF07 => self.timer.write_tac(value),
+            0xFF0F => self.interrupts.write_flags(value),
+            0xFF10..=0xFF3F => self.apu.write_register(address, value),
+            0xFF40..=0xFF45 | 0xFF47..=0xFF4B => self.ppu.write_register(address, value),
+            0xFF46 => {
+                self.io[(address - 0xFF00) as usize] = value;
+                self.dma_transfer(value);
+            },
+            // a non-zero write unmaps the boot ROM until the next reset
+            0xFF50 => {
+                if value != 0 {
+                    self.boot_rom = None;
+                }
+            },
+            _ => self.io[(address - 0xFF00) as usize] = value,
+        }
+    }
+
+    // OAM DMA copies 0xXX00 - 0xXX9F into OAM, done instantly here
+    fn dma_transfer(&mut self, source: u8) {
+        let base = (source as u16) << 8;
+        for i in 0..0xA0 {
+            self.ppu.oam[i as usize] = self.read_byte(base + i);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_memory_bus;
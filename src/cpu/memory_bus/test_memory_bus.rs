@@ -0,0 +1,139 @@
+use super::*;
+use crate::cpu::ppu::ppu_registers::{LCDMode, PPUControlRegister, PPUStatusRegister};
+
+#[test]
+fn test_rom_read_only() {
+    let mut bus = MemoryBus::new();
+    bus.cartridge.rom[0x1234] = 0xAB;
+    bus.write_byte(0x1234, 0x00);
+    assert_eq!(bus.read_byte(0x1234), 0xAB);
+}
+
+#[test]
+fn test_echo_ram() {
+    let mut bus = MemoryBus::new();
+    bus.write_byte(0xC123, 0x11);
+    assert_eq!(bus.read_byte(0xE123), 0x11);
+    bus.write_byte(0xFDFF, 0x22);
+    assert_eq!(bus.read_byte(0xDDFF), 0x22);
+}
+
+#[test]
+fn test_unusable_region() {
+    let mut bus = MemoryBus::new();
+    bus.write_byte(0xFEA0, 0x11);
+    assert_eq!(bus.read_byte(0xFEA0), 0x00);
+}
+
+#[test]
+fn test_hram_and_ie() {
+    let mut bus = MemoryBus::new();
+    bus.write_byte(0xFF80, 0x11);
+    bus.write_byte(0xFFFE, 0x22);
+    bus.write_byte(0xFFFF, 0x1F);
+    assert_eq!(bus.read_byte(0xFF80), 0x11);
+    assert_eq!(bus.read_byte(0xFFFE), 0x22);
+    assert_eq!(bus.read_byte(0xFFFF), 0x1F);
+    assert_eq!(bus.interrupts.enable, 0x1F);
+}
+
+#[test]
+fn test_oam_dma() {
+    let mut bus = MemoryBus::new();
+    for i in 0..0xA0 {
+        bus.write_byte(0xC100 + i, i as u8);
+    }
+    bus.write_byte(0xFF46, 0xC1);
+    assert_eq!(bus.read_byte(0xFE00), 0x00);
+    assert_eq!(bus.read_byte(0xFE9F), 0x9F);
+    assert_eq!(bus.read_byte(0xFF46), 0xC1);
+}
+
+#[test]
+fn test_boot_rom_overlay() {
+    let mut bus = MemoryBus::new();
+    bus.cartridge.rom[0x0000] = 0x11;
+    bus.cartridge.rom[0x0100] = 0x22;
+    bus.boot_rom = Some([0x31; 0x100]);
+    assert_eq!(bus.read_byte(0x0000), 0x31);
+    assert_eq!(bus.read_byte(0x00FF), 0x31);
+    assert_eq!(bus.read_byte(0x0100), 0x22);
+
+    // writing zero leaves it mapped
+    bus.write_byte(0xFF50, 0x00);
+    assert_eq!(bus.read_byte(0x0000), 0x31);
+    bus.write_byte(0xFF50, 0x01);
+    assert_eq!(bus.read_byte(0x0000), 0x11);
+}
+
+#[test]
+fn test_post_boot_io() {
+    let mut bus = MemoryBus::new();
+    bus.init_post_boot();
+    assert_eq!(bus.read_byte(0xFF04), 0xAB);
+    assert_eq!(bus.read_byte(0xFF07), 0xF8);
+    assert_eq!(bus.read_byte(0xFF0F), 0xE1);
+    assert_eq!(bus.read_byte(0xFF26), 0xF1);
+    assert_eq!(bus.read_byte(0xFF25), 0xF3);
+    assert_eq!(bus.read_byte(0xFF40), 0x91);
+    assert_eq!(bus.read_byte(0xFF47), 0xFC);
+    assert_eq!(bus.read_byte(0xFFFF), 0x00);
+}
+
+#[test]
+fn test_post_boot_ppu_registers() {
+    let mut bus = MemoryBus::new();
+    for address in [0xFF42, 0xFF43, 0xFF45, 0xFF47, 0xFF4A, 0xFF4B] {
+        bus.write_byte(address, 0x5A);
+    }
+
+    // the post boot values go through the PPU, not the plain IO bytes
+    bus.init_post_boot();
+    assert_eq!(bus.read_byte(0xFF40), 0x91);
+    assert_eq!(bus.read_byte(0xFF42), 0x00);
+    assert_eq!(bus.read_byte(0xFF43), 0x00);
+    assert_eq!(bus.read_byte(0xFF45), 0x00);
+    assert_eq!(bus.read_byte(0xFF47), 0xFC);
+    assert_eq!(bus.read_byte(0xFF4A), 0x00);
+    assert_eq!(bus.read_byte(0xFF4B), 0x00);
+    for address in [0xFF40, 0xFF42, 0xFF43, 0xFF45, 0xFF47, 0xFF4A, 0xFF4B] {
+        assert_eq!(bus.io[(address - 0xFF00) as usize], 0x00);
+    }
+}
+
+#[test]
+fn test_lcdc_round_trip() {
+    let mut bus = MemoryBus::new();
+    for value in 0..=0xFF {
+        bus.write_byte(0xFF40, value);
+        assert_eq!(bus.read_byte(0xFF40), value);
+    }
+
+    // bit 5 enables the window, bit 4 selects the 0x8000 tile data
+    let control = PPUControlRegister::from(0x20);
+    assert!(control.win_en);
+    assert!(!control.tile_sel);
+    let control = PPUControlRegister::from(0x10);
+    assert!(!control.win_en);
+    assert!(control.tile_sel);
+}
+
+#[test]
+fn test_stat_read_only_bits() {
+    let mut bus = MemoryBus::new();
+    let read_only = bus.read_byte(0xFF41) & 0b0000_0111;
+
+    // bit 7 reads 1, interrupt selects are writable, mode and coincidence aren't
+    bus.write_byte(0xFF41, 0x7F);
+    assert_eq!(bus.read_byte(0xFF41), 0xF8 | read_only);
+    bus.write_byte(0xFF41, 0x00);
+    assert_eq!(bus.read_byte(0xFF41), 0x80 | read_only);
+
+    // the mode is reported in bits 0 - 1 as 0 HBlank, 1 VBlank, 2 OAM scan, 3 drawing
+    let mut status = PPUStatusRegister::new();
+    for (mode, bits) in [(LCDMode::HBlank, 0), (LCDMode::VBlank, 1), (LCDMode::OAMScan, 2), (LCDMode::Drawing, 3)] {
+        status.set_mode(mode);
+        assert_eq!(u8::from(status), bits);
+        assert_eq!(PPUStatusRegister::from(bits).mode(), mode);
+    }
+}
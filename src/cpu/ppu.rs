@@ -1,98 +1,260 @@
-use futures::executor;
-
 pub mod ppu_registers;
 pub use ppu_registers::*;
 
-#[cfg(target_arch="wasm32")]
-use wasm_bindgen::prelude::*;
+pub mod tiles;
+
+pub mod sprites;
+
+use super::{Interrupt, InterruptController};
 
-use winit::event;
-use winit::event_loop::{ControlFlow, EventLoop};
-use winit::window::{Window, WindowBuilder};
-use winit_input_helper::WinitInputHelper;
-use winit::dpi::{LogicalSize, PhysicalSize};
+pub const SCREEN_WIDTH:  usize = 160;
+pub const SCREEN_HEIGHT: usize = 144;
 
-use pixels::{Error, Pixels, SurfaceTexture, raw_window_handle::HasRawWindowHandle};
+// every line takes 456 dots, 144 visible lines are followed by 10 lines of VBlank
+pub const DOTS_PER_LINE:   u16 = 456;
+pub const LINES_PER_FRAME: u8  = 154;
+pub const DOTS_PER_FRAME:  u64 = DOTS_PER_LINE as u64 * LINES_PER_FRAME as u64;
+const OAM_SCAN_DOTS: u16 = 80;
+const DRAWING_DOTS:  u16 = 172;
 
+// Pixel processing unit, owns video memory and draws into a framebuffer of shades
+// presenting the framebuffer is left to a frontend
 pub struct PPU {
-    input: WinitInputHelper,
-    pixels: Pixels,
-    window: Window,
+    pub vram: [u8; 0x2000],
+    pub oam: [u8; 0xA0],
     control_reg: PPUControlRegister,
     status_reg: PPUStatusRegister,
     vertical_scroll_reg: VerticalScrollRegister,
     horizontal_scroll_reg: HorizontalScrollRegister,
     scaline_reg: ScanlineRegister,
     scanline_compare_reg: ScanlineCompareRegister,
+    bg_palette_reg: PaletteRegister,
+    obj_palette_regs: [PaletteRegister; 2], // OBP0, OBP1
+    window_y_reg: WindowYRegister,
+    window_x_reg: WindowXRegister,
+    window_y_triggered: bool, // LY matched WY at some point this frame
+    window_line: u8, // window row to draw next, only advances on lines showing the window
+    dot: u16, // position within the current line
+    stat_line: bool, // combined STAT interrupt sources, interrupts fire on its rising edge
+    pub frame_complete: bool, // set on entering VBlank, cleared by whoever presents the frame
+    pub framebuffer: [u8; SCREEN_WIDTH * SCREEN_HEIGHT], // shades 0 - 3
 }
 
 impl PPU {
-    pub fn new(event_loop: &EventLoop<()>) -> PPU {
-        let window = WindowBuilder::new()
-            .with_title("rusty-gb")
-            .with_inner_size(LogicalSize::new(1000.0, 1000.0))
-            .build(&event_loop)
-            .unwrap();
-
-        let window_size = window.inner_size();
-        let surface_texture = SurfaceTexture::new(window_size.width, window_size.height, &window);
-
-        // append window to web canvas
-        #[cfg(target_arch = "wasm32")]
-            PPU::append_window_to_web_canvas(&window);
-
-        let pixels = executor::block_on(
-            Pixels::new_async(320, 320, surface_texture))
-            .unwrap();
-        let input = WinitInputHelper::new();
-
+    pub fn new() -> PPU {
         PPU {
-            input,
-            pixels,
-            window,
+            vram: [0; 0x2000],
+            oam: [0; 0xA0],
             control_reg: PPUControlRegister::new(),
             status_reg: PPUStatusRegister::new(),
             vertical_scroll_reg: VerticalScrollRegister { scy: 0 },
             horizontal_scroll_reg: HorizontalScrollRegister { scx: 0 },
             scaline_reg: ScanlineRegister { ly: 0 },
             scanline_compare_reg: ScanlineCompareRegister { lyc: 0 },
+            bg_palette_reg: PaletteRegister::new(),
+            obj_palette_regs: [PaletteRegister::new(), PaletteRegister::new()],
+            window_y_reg: WindowYRegister { wy: 0 },
+            window_x_reg: WindowXRegister { wx: 0 },
+            window_y_triggered: false,
+            window_line: 0,
+            dot: 0,
+            stat_line: false,
+            frame_complete: false,
+            framebuffer: [0; SCREEN_WIDTH * SCREEN_HEIGHT],
         }
     }
 
-    pub fn render(&mut self) -> Result<(), Error> {
-        self.populate_frame_random_rgba();
-        self.pixels.render()
+    pub fn read_register(&self, address: u16) -> u8 {
+        match address {
+            0xFF40 => u8::from(self.control_reg),
+            // bit 7 of STAT is unused and reads as 1
+            0xFF41 => u8::from(self.status_reg) | 0x80,
+            0xFF42 => self.vertical_scroll_reg.scy,
+            0xFF43 => self.horizontal_scroll_reg.scx,
+            0xFF44 => self.scaline_reg.ly,
+            0xFF45 => self.scanline_compare_reg.lyc,
+            0xFF47 => u8::from(self.bg_palette_reg),
+            0xFF48 => u8::from(self.obj_palette_regs[0]),
+            0xFF49 => u8::from(self.obj_palette_regs[1]),
+            0xFF4A => self.window_y_reg.wy,
+            0xFF4B => self.window_x_reg.wx,
+            _ => 0xFF,
+        }
     }
-    
-    pub fn request_refresh(&mut self) {
-        self.window.request_redraw();
+
+    pub fn write_register(&mut self, address: u16, value: u8) {
+        match address {
+            0xFF40 => {
+                let was_enabled = self.control_reg.lcd_en;
+                self.control_reg = PPUControlRegister::from(value);
+                if was_enabled != self.control_reg.lcd_en {
+                    self.restart_lcd();
+                }
+            },
+            0xFF41 => {
+                // mode and LYC coincidence bits are read only
+                let read_only = u8::from(self.status_reg) & 0b0000_0111;
+                self.status_reg = PPUStatusRegister::from((value & 0b0111_1000) | read_only);
+            },
+            0xFF42 => self.vertical_scroll_reg.scy = value,
+            0xFF43 => self.horizontal_scroll_reg.scx = value,
+            // LY is read only
+            0xFF44 => {},
+            0xFF45 => self.scanline_compare_reg.lyc = value,
+            0xFF47 => self.bg_palette_reg = PaletteRegister::from(value),
+            0xFF48 => self.obj_palette_regs[0] = PaletteRegister::from(value),
+            0xFF49 => self.obj_palette_regs[1] = PaletteRegister::from(value),
+            0xFF4A => self.window_y_reg.wy = value,
+            0xFF4B => self.window_x_reg.wx = value,
+            _ => {},
+        }
     }
 
-    #[cfg(target_arch="wasm32")]
-    fn append_window_to_web_canvas(window: &Window) {
-        // set window size manually (winit prevents sizing with CSS)
-        // use winit::dpi::PhysicalSize;
-        // window.set_inner_size(PhysicalSize::new(1000, 1000));
-        
-        use winit::platform::web::WindowExtWebSys;
-        web_sys::window()
-            .and_then(|win| win.document())
-            .and_then(|doc| {
-                let dst = doc.get_element_by_id("rusty_gb_body")?;
-                let canvas = web_sys::Element::from(window.canvas());
-                dst.append_child(&canvas).ok()?;
-                Some(())
-            })
-            .expect("Unable to append to canvas")
+    // Advance by cycles dots, walking OAM scan, drawing and HBlank on visible lines then VBlank
+    pub fn tick(&mut self, cycles: u8, interrupts: &mut InterruptController) {
+        if !self.control_reg.lcd_en {
+            return;
+        }
+
+        for _ in 0..cycles {
+            self.dot += 1;
+            if self.dot == DOTS_PER_LINE {
+                self.dot = 0;
+                self.scaline_reg.ly = (self.scaline_reg.ly + 1) % LINES_PER_FRAME;
+            }
+
+            let ly = self.scaline_reg.ly;
+            let mode = match (ly as usize, self.dot) {
+                (SCREEN_HEIGHT.., _) => LCDMode::VBlank,
+                (_, 0..OAM_SCAN_DOTS) => LCDMode::OAMScan,
+                (_, dot) if dot < OAM_SCAN_DOTS + DRAWING_DOTS => LCDMode::Drawing,
+                _ => LCDMode::HBlank,
+            };
+            if mode != self.status_reg.mode() {
+                match mode {
+                    // the line is drawn in one go once its pixels have been pushed
+                    LCDMode::HBlank => self.render_scanline(ly),
+                    LCDMode::VBlank => {
+                        self.frame_complete = true;
+                        interrupts.request(Interrupt::VBlank);
+                    },
+                    _ => {},
+                }
+                self.status_reg.set_mode(mode);
+            }
+
+            self.status_reg.lyc_stat = ly == self.scanline_compare_reg.lyc;
+            self.update_stat_line(interrupts);
+        }
     }
 
-    fn populate_frame_random_rgba(&mut self) {
-        // update frame with random rgba values
-        let frame: &mut [u8] = self.pixels.get_frame_mut();
-        let mut i = rand::random::<usize>() % frame.len();
-        i = i - (i % 4);
+    // STAT sources are ORed into one line and only its rising edge requests an interrupt,
+    // so a source becoming true while another already holds the line high is blocked
+    fn update_stat_line(&mut self, interrupts: &mut InterruptController) {
+        let stat = self.status_reg;
+        let mode = stat.mode();
+        let line = (stat.intr_lyc && stat.lyc_stat)
+            || (stat.intr_m0 && mode == LCDMode::HBlank)
+            || (stat.intr_m1 && mode == LCDMode::VBlank)
+            || (stat.intr_m2 && mode == LCDMode::OAMScan);
 
-        let rgba = [rand::random::<u8>(), rand::random::<u8>(), rand::random::<u8>(), 255];
-        frame[i..i+4].copy_from_slice(&rgba);
+        if line && !self.stat_line {
+            interrupts.request(Interrupt::LCDStat);
+        }
+        self.stat_line = line;
+    }
+
+    // Switching the LCD on or off restarts at the top of the frame, a disabled LCD shows blank
+    fn restart_lcd(&mut self) {
+        self.dot = 0;
+        self.scaline_reg.ly = 0;
+        if self.control_reg.lcd_en {
+            self.status_reg.set_mode(LCDMode::OAMScan);
+        } else {
+            self.status_reg.set_mode(LCDMode::HBlank);
+            self.stat_line = false;
+            self.framebuffer.fill(0);
+        }
     }
-}
\ No newline at end of file
+
+    // Draw one line of the framebuffer from VRAM and OAM with the current registers
+    pub fn render_scanline(&mut self, line: u8) {
+        let vram = &self.vram;
+        if line == 0 {
+            self.window_y_triggered = false;
+            self.window_line = 0;
+        }
+        if line == self.window_y_reg.wy {
+            self.window_y_triggered = true;
+        }
+
+        let start = line as usize * SCREEN_WIDTH;
+        let row = &mut self.framebuffer[start..start + SCREEN_WIDTH];
+
+        // with the LCD off the line is blank
+        if !self.control_reg.lcd_en {
+            row.fill(0);
+            return;
+        }
+
+        let control = self.control_reg;
+        let palette = self.bg_palette_reg;
+        // background color IDs before the palette, sprites check them for priority
+        let mut bg_color_ids = [0u8; SCREEN_WIDTH];
+
+        // with the background off both it and the window are blank
+        if control.bg_en {
+            let bg_map = if control.bg_map { tiles::TILE_MAP_1 } else { tiles::TILE_MAP_0 };
+            // the 256x256 background wraps around in both directions
+            let y = line.wrapping_add(self.vertical_scroll_reg.scy);
+            for (screen_x, color_id) in bg_color_ids.iter_mut().enumerate() {
+                let x = (screen_x as u8).wrapping_add(self.horizontal_scroll_reg.scx);
+                let tile_index = tiles::tile_map_index(vram, bg_map, x, y);
+                let tile_address = tiles::tile_data_address(tile_index, control.tile_sel);
+                *color_id = tiles::tile_pixel(vram, tile_address, y % 8, x % 8);
+            }
+
+            // window covers the background from WX - 7 to the right edge, WX above 166 hides it
+            let wx = self.window_x_reg.wx;
+            if control.win_en && self.window_y_triggered && wx <= 166 {
+                let win_map = if control.win_map { tiles::TILE_MAP_1 } else { tiles::TILE_MAP_0 };
+                let y = self.window_line;
+                let left = (wx as usize).saturating_sub(7);
+                for (screen_x, color_id) in bg_color_ids.iter_mut().enumerate().skip(left) {
+                    let x = (screen_x + 7 - wx as usize) as u8;
+                    let tile_index = tiles::tile_map_index(vram, win_map, x, y);
+                    let tile_address = tiles::tile_data_address(tile_index, control.tile_sel);
+                    *color_id = tiles::tile_pixel(vram, tile_address, y % 8, x % 8);
+                }
+                self.window_line += 1;
+            }
+        }
+
+        for (pixel, &color_id) in row.iter_mut().zip(bg_color_ids.iter()) {
+            *pixel = palette.shade(color_id);
+        }
+
+        if !control.obj_en {
+            return;
+        }
+        let height = if control.obj_size { 16 } else { 8 };
+        let sprites = sprites::scan_oam(&self.oam, line, height);
+        for (screen_x, pixel) in row.iter_mut().enumerate() {
+            // the first opaque sprite pixel in priority order wins, even if the background then hides it
+            let opaque = sprites.iter().find_map(|sprite| {
+                match sprite.pixel(vram, line, screen_x as u8, height) {
+                    Some(color_id) if color_id != 0 => Some((sprite, color_id)),
+                    _ => None,
+                }
+            });
+            if let Some((sprite, color_id)) = opaque {
+                if !sprite.attributes.bg_priority || bg_color_ids[screen_x] == 0 {
+                    *pixel = self.obj_palette_regs[sprite.attributes.palette as usize].shade(color_id);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_ppu;
@@ -32,8 +32,8 @@ impl PPUControlRegister {
 
 const LCD_EN_BYTE_POSITION:   u8 = 7;
 const WIN_MAP_BYTE_POSITION:  u8 = 6;
-const WIN_EN_BYTE_POSITION:   u8 = 6;
-const TILE_SEL_BYTE_POSITION: u8 = 5;
+const WIN_EN_BYTE_POSITION:   u8 = 5;
+const TILE_SEL_BYTE_POSITION: u8 = 4;
 const BG_MAP_BYTE_POSITION:   u8 = 3;
 const OBJ_SIZE_BYTE_POSITION: u8 = 2;
 const OBJ_EN_BYTE_POSITION:   u8 = 1;
@@ -101,6 +101,35 @@ impl PPUStatusRegister {
     }
 }
 
+// PPU modes as reported in the low 2 bits of STAT
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum LCDMode {
+    HBlank,  // 0
+    VBlank,  // 1
+    OAMScan, // 2
+    Drawing, // 3
+}
+
+impl PPUStatusRegister {
+    pub fn mode(&self) -> LCDMode {
+        match self.lcd_mode {
+            [false, false] => LCDMode::HBlank,
+            [true,  false] => LCDMode::VBlank,
+            [false, true ] => LCDMode::OAMScan,
+            [true,  true ] => LCDMode::Drawing,
+        }
+    }
+
+    pub fn set_mode(&mut self, mode: LCDMode) {
+        self.lcd_mode = match mode {
+            LCDMode::HBlank  => [false, false],
+            LCDMode::VBlank  => [true,  false],
+            LCDMode::OAMScan => [false, true ],
+            LCDMode::Drawing => [true,  true ],
+        };
+    }
+}
+
 const INTR_LYC_BYTE_POSITION: u8 = 6;
 const INTR_M2_BYTE_POSITION:  u8 = 5;
 const INTR_M1_BYTE_POSITION:  u8 = 4;
@@ -128,8 +157,8 @@ impl convert::From<u8> for PPUStatusRegister {
         let intr_m0  = ((byte >> INTR_M0_BYTE_POSITION)  & 0b1) != 0;
         let lyc_stat = ((byte >> LYC_STAT_BYTE_POSITION) & 0b1) != 0;
         let lcd_mode = [
-            ((byte >> LCD_MODE_BYTE_POSITION) & 0b1) != 0,
             (byte & 0b1) != 0,
+            ((byte >> LCD_MODE_BYTE_POSITION) & 0b1) != 0,
         ];
 
         PPUStatusRegister {
@@ -165,4 +194,48 @@ pub struct ScanlineRegister {
 #[derive(Copy, Clone)]
 pub struct ScanlineCompareRegister {
     pub lyc: u8
-}
\ No newline at end of file
+}
+// Window Y Position Register (WY) 0xFF4A
+#[derive(Copy, Clone)]
+pub struct WindowYRegister {
+    pub wy: u8
+}
+
+// Window X Position Register (WX) 0xFF4B, the window starts at WX - 7
+#[derive(Copy, Clone)]
+pub struct WindowXRegister {
+    pub wx: u8
+}
+
+// Palette Register (BGP) 0xFF47
+// 2 bits per color ID, color 0 in the lowest bits, 0 is lightest and 3 is darkest
+#[derive(Copy, Clone)]
+pub struct PaletteRegister {
+    pub shades: [u8; 4],
+}
+
+impl PaletteRegister {
+    pub fn new() -> PaletteRegister {
+        PaletteRegister {
+            shades: [0, 0, 0, 0],
+        }
+    }
+
+    pub fn shade(&self, color_id: u8) -> u8 {
+        self.shades[color_id as usize]
+    }
+}
+
+impl convert::From<PaletteRegister> for u8 {
+    fn from(reg: PaletteRegister) -> u8 {
+        reg.shades[3] << 6 | reg.shades[2] << 4 | reg.shades[1] << 2 | reg.shades[0]
+    }
+}
+
+impl convert::From<u8> for PaletteRegister {
+    fn from(byte: u8) -> Self {
+        PaletteRegister {
+            shades: [byte & 0b11, (byte >> 2) & 0b11, (byte >> 4) & 0b11, (byte >> 6) & 0b11],
+        }
+    }
+}
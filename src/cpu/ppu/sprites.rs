@@ -0,0 +1,91 @@
+use std::convert;
+
+// OAM holds 40 sprites of 4 bytes: Y + 16, X + 8, tile index, attributes
+pub const OAM_SPRITES:      usize = 40;
+pub const SPRITES_PER_LINE: usize = 10;
+
+const BG_PRIORITY_BYTE_POSITION: u8 = 7;
+const Y_FLIP_BYTE_POSITION:      u8 = 6;
+const X_FLIP_BYTE_POSITION:      u8 = 5;
+const PALETTE_BYTE_POSITION:     u8 = 4;
+
+// Sprite attribute flags, byte 3 of an OAM entry
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct SpriteAttributes {
+    pub bg_priority: bool, // background colors 1 - 3 are drawn over the sprite
+    pub y_flip:      bool,
+    pub x_flip:      bool,
+    pub palette:     bool, // OBP1 when set, OBP0 otherwise
+}
+
+impl convert::From<u8> for SpriteAttributes {
+    fn from(byte: u8) -> Self {
+        let bg_priority = ((byte >> BG_PRIORITY_BYTE_POSITION) & 0b1) != 0;
+        let y_flip      = ((byte >> Y_FLIP_BYTE_POSITION)      & 0b1) != 0;
+        let x_flip      = ((byte >> X_FLIP_BYTE_POSITION)      & 0b1) != 0;
+        let palette     = ((byte >> PALETTE_BYTE_POSITION)     & 0b1) != 0;
+
+        SpriteAttributes {
+            bg_priority,
+            y_flip,
+            x_flip,
+            palette,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Sprite {
+    pub y: u8, // screen Y + 16
+    pub x: u8, // screen X + 8
+    pub tile: u8,
+    pub attributes: SpriteAttributes,
+    pub index: usize, // position in OAM
+}
+
+impl Sprite {
+    pub fn from_oam(oam: &[u8], index: usize) -> Sprite {
+        let entry = &oam[index * 4..index * 4 + 4];
+        Sprite {
+            y: entry[0],
+            x: entry[1],
+            tile: entry[2],
+            attributes: SpriteAttributes::from(entry[3]),
+            index,
+        }
+    }
+
+    // Color ID 0 - 3 at screen_x on line, None outside the sprite
+    pub fn pixel(&self, vram: &[u8], line: u8, screen_x: u8, height: u8) -> Option<u8> {
+        let col = (screen_x as i16 + 8 - self.x as i16) as u8;
+        let row = (line as i16 + 16 - self.y as i16) as u8;
+        if col >= 8 || row >= height {
+            return None;
+        }
+
+        let col = if self.attributes.x_flip { 7 - col } else { col };
+        let row = if self.attributes.y_flip { height - 1 - row } else { row };
+        // 8x16 sprites ignore bit 0 of the tile index, the bottom half is the next tile
+        let tile = if height == 16 { self.tile & 0xFE } else { self.tile };
+        // sprites always use 0x8000 based unsigned tile indices
+        Some(super::tiles::tile_pixel(vram, super::tiles::tile_data_address(tile, true), row, col))
+    }
+}
+
+// Sprites on line in drawing priority order, at most 10 in OAM order are picked
+// on DMG the smaller X wins, then the lower OAM index
+pub fn scan_oam(oam: &[u8], line: u8, height: u8) -> Vec<Sprite> {
+    let mut sprites: Vec<Sprite> = (0..OAM_SPRITES)
+        .map(|index| Sprite::from_oam(oam, index))
+        .filter(|sprite| {
+            let row = line as i16 + 16 - sprite.y as i16;
+            (0..height as i16).contains(&row)
+        })
+        .take(SPRITES_PER_LINE)
+        .collect();
+    sprites.sort_by_key(|sprite| (sprite.x, sprite.index));
+    sprites
+}
+
+#[cfg(test)]
+mod test_sprites;
@@ -0,0 +1,80 @@
+use super::*;
+
+fn set_sprite(oam: &mut [u8], index: usize, y: u8, x: u8, tile: u8, attributes: u8) {
+    oam[index * 4..index * 4 + 4].copy_from_slice(&[y, x, tile, attributes]);
+}
+
+#[test]
+fn test_attributes() {
+    let attributes = SpriteAttributes::from(0b1011_0000);
+    assert!(attributes.bg_priority);
+    assert!(!attributes.y_flip);
+    assert!(attributes.x_flip);
+    assert!(attributes.palette);
+}
+
+#[test]
+fn test_scan_limit() {
+    let mut oam = [0; 0xA0];
+    for index in 0..12 {
+        set_sprite(&mut oam, index, 16, 8 + index as u8, 0, 0);
+    }
+    // off screen horizontally still counts toward the limit
+    set_sprite(&mut oam, 0, 16, 0, 0, 0);
+
+    let sprites = scan_oam(&oam, 0, 8);
+    assert_eq!(sprites.len(), 10);
+    assert_eq!(sprites[0].index, 0);
+    assert!(sprites.iter().all(|sprite| sprite.index < 10));
+}
+
+#[test]
+fn test_scan_height() {
+    let mut oam = [0; 0xA0];
+    set_sprite(&mut oam, 0, 16, 8, 0, 0);
+    assert_eq!(scan_oam(&oam, 7, 8).len(), 1);
+    assert_eq!(scan_oam(&oam, 8, 8).len(), 0);
+    assert_eq!(scan_oam(&oam, 15, 16).len(), 1);
+    assert_eq!(scan_oam(&oam, 16, 16).len(), 0);
+}
+
+#[test]
+fn test_scan_priority() {
+    let mut oam = [0; 0xA0];
+    set_sprite(&mut oam, 0, 16, 20, 0, 0);
+    set_sprite(&mut oam, 1, 16, 10, 0, 0);
+    set_sprite(&mut oam, 2, 16, 10, 0, 0);
+
+    let order: Vec<usize> = scan_oam(&oam, 0, 8).iter().map(|sprite| sprite.index).collect();
+    assert_eq!(order, vec![1, 2, 0]);
+}
+
+#[test]
+fn test_sprite_pixel_flips() {
+    let mut vram = [0; 0x2000];
+    // tile 2 row 0 has only the leftmost pixel set to 3
+    vram[0x20] = 0x80;
+    vram[0x21] = 0x80;
+
+    let mut sprite = Sprite::from_oam(&[16, 8, 2, 0], 0);
+    assert_eq!(sprite.pixel(&vram, 0, 0, 8), Some(3));
+    assert_eq!(sprite.pixel(&vram, 0, 7, 8), Some(0));
+    assert_eq!(sprite.pixel(&vram, 0, 8, 8), None);
+
+    sprite.attributes.x_flip = true;
+    assert_eq!(sprite.pixel(&vram, 0, 7, 8), Some(3));
+
+    sprite.attributes.y_flip = true;
+    assert_eq!(sprite.pixel(&vram, 7, 7, 8), Some(3));
+}
+
+#[test]
+fn test_tall_sprite() {
+    let mut vram = [0; 0x2000];
+    // tile 3 row 0 is the bottom half of an 8x16 sprite using tile 2
+    vram[0x30] = 0x80;
+
+    let sprite = Sprite::from_oam(&[16, 8, 3, 0], 0);
+    assert_eq!(sprite.pixel(&vram, 8, 0, 16), Some(1));
+    assert_eq!(sprite.pixel(&vram, 0, 0, 16), Some(0));
+}
@@ -0,0 +1,372 @@
+use super::*;
+
+fn tick_dots(ppu: &mut PPU, interrupts: &mut InterruptController, dots: u32) {
+    for _ in 0..dots / 4 {
+        ppu.tick(4, interrupts);
+    }
+    ppu.tick((dots % 4) as u8, interrupts);
+}
+
+// tile 1 is solid color 3, tile 2 is solid color 1
+fn solid_tiles(ppu: &mut PPU) {
+    ppu.vram[0x10..0x20].fill(0xFF);
+    for row in 0..8 {
+        ppu.vram[0x20 + row * 2] = 0xFF;
+    }
+}
+
+fn pixel(ppu: &PPU, x: usize, y: usize) -> u8 {
+    ppu.framebuffer[y * SCREEN_WIDTH + x]
+}
+
+#[test]
+fn test_mode_timing() {
+    let mut ppu = PPU::new();
+    let mut interrupts = InterruptController::new();
+    ppu.write_register(0xFF40, 0x80);
+    assert_eq!(ppu.read_register(0xFF41) & 0b11, 2);
+
+    tick_dots(&mut ppu, &mut interrupts, 80);
+    assert_eq!(ppu.read_register(0xFF41) & 0b11, 3);
+    tick_dots(&mut ppu, &mut interrupts, 172);
+    assert_eq!(ppu.read_register(0xFF41) & 0b11, 0);
+    tick_dots(&mut ppu, &mut interrupts, 204);
+    assert_eq!(ppu.read_register(0xFF41) & 0b11, 2);
+    assert_eq!(ppu.read_register(0xFF44), 1);
+}
+
+#[test]
+fn test_vblank_and_frame_length() {
+    let mut ppu = PPU::new();
+    let mut interrupts = InterruptController::new();
+    ppu.write_register(0xFF40, 0x80);
+
+    tick_dots(&mut ppu, &mut interrupts, 144 * 456 - 4);
+    assert!(!ppu.frame_complete);
+    assert_eq!(interrupts.flags, 0);
+
+    tick_dots(&mut ppu, &mut interrupts, 4);
+    assert_eq!(ppu.read_register(0xFF44), 144);
+    assert_eq!(ppu.read_register(0xFF41) & 0b11, 1);
+    assert!(ppu.frame_complete);
+    assert_eq!(interrupts.flags, Interrupt::VBlank.mask());
+
+    tick_dots(&mut ppu, &mut interrupts, 10 * 456);
+    assert_eq!(ppu.read_register(0xFF44), 0);
+    assert_eq!(ppu.read_register(0xFF41) & 0b11, 2);
+}
+
+#[test]
+fn test_full_frame() {
+    let mut ppu = PPU::new();
+    let mut interrupts = InterruptController::new();
+    ppu.write_register(0xFF40, 0x80);
+
+    // every visible line walks OAM scan 80, drawing 172, HBlank 204 dots
+    for line in 0..SCREEN_HEIGHT as u8 {
+        assert_eq!(ppu.read_register(0xFF44), line);
+        let mut modes = Vec::new();
+        for _ in 0..DOTS_PER_LINE / 4 {
+            modes.push(ppu.read_register(0xFF41) & 0b11);
+            ppu.tick(4, &mut interrupts);
+        }
+        assert!(modes[..20].iter().all(|&mode| mode == 2));
+        assert!(modes[20..63].iter().all(|&mode| mode == 3));
+        assert!(modes[63..].iter().all(|&mode| mode == 0));
+    }
+
+    // lines 144 - 153 are VBlank, then LY wraps to 0 after 456 x 154 dots
+    for line in SCREEN_HEIGHT as u8..LINES_PER_FRAME {
+        assert_eq!(ppu.read_register(0xFF44), line);
+        assert_eq!(ppu.read_register(0xFF41) & 0b11, 1);
+        tick_dots(&mut ppu, &mut interrupts, DOTS_PER_LINE as u32);
+    }
+    assert_eq!(ppu.read_register(0xFF44), 0);
+    assert_eq!(ppu.read_register(0xFF41) & 0b11, 2);
+    assert_eq!(DOTS_PER_FRAME, 456 * 154);
+
+    // frame_complete is raised once per frame and left for the CPU to clear
+    assert!(ppu.frame_complete);
+    ppu.frame_complete = false;
+    tick_dots(&mut ppu, &mut interrupts, DOTS_PER_FRAME as u32 - 4);
+    assert!(ppu.frame_complete);
+    ppu.frame_complete = false;
+    tick_dots(&mut ppu, &mut interrupts, 4);
+    assert!(!ppu.frame_complete);
+    assert_eq!(ppu.read_register(0xFF44), 0);
+}
+
+#[test]
+fn test_lcd_off() {
+    let mut ppu = PPU::new();
+    let mut interrupts = InterruptController::new();
+    ppu.write_register(0xFF40, 0x80);
+    tick_dots(&mut ppu, &mut interrupts, 1000);
+
+    ppu.write_register(0xFF40, 0x00);
+    assert_eq!(ppu.read_register(0xFF44), 0);
+    assert_eq!(ppu.read_register(0xFF41) & 0b11, 0);
+    tick_dots(&mut ppu, &mut interrupts, 1000);
+    assert_eq!(ppu.read_register(0xFF44), 0);
+}
+
+#[test]
+fn test_lyc_interrupt() {
+    let mut ppu = PPU::new();
+    let mut interrupts = InterruptController::new();
+    ppu.write_register(0xFF45, 2);
+    ppu.write_register(0xFF41, 0x40);
+    ppu.write_register(0xFF40, 0x80);
+
+    tick_dots(&mut ppu, &mut interrupts, 2 * 456 - 4);
+    assert_eq!(interrupts.flags, 0);
+    assert_eq!(ppu.read_register(0xFF41) & 0x04, 0);
+
+    tick_dots(&mut ppu, &mut interrupts, 4);
+    assert_eq!(interrupts.flags, Interrupt::LCDStat.mask());
+    assert_eq!(ppu.read_register(0xFF41) & 0x04, 0x04);
+}
+
+#[test]
+fn test_stat_blocking() {
+    let mut ppu = PPU::new();
+    let mut interrupts = InterruptController::new();
+    // HBlank and OAM scan sources
+    ppu.write_register(0xFF41, 0x28);
+    ppu.write_register(0xFF40, 0x80);
+
+    tick_dots(&mut ppu, &mut interrupts, 4);
+    assert_eq!(interrupts.flags, Interrupt::LCDStat.mask());
+    interrupts.acknowledge(Interrupt::LCDStat);
+
+    // the line drops while drawing, then rises again for HBlank
+    tick_dots(&mut ppu, &mut interrupts, 252);
+    assert_eq!(interrupts.flags, Interrupt::LCDStat.mask());
+    interrupts.acknowledge(Interrupt::LCDStat);
+
+    // HBlank straight into OAM scan keeps the line high
+    tick_dots(&mut ppu, &mut interrupts, 204);
+    assert_eq!(ppu.read_register(0xFF44), 1);
+    assert_eq!(interrupts.flags, 0);
+}
+
+#[test]
+fn test_lyc_coincidence() {
+    let mut ppu = PPU::new();
+    let mut interrupts = InterruptController::new();
+    ppu.write_register(0xFF45, 153);
+    ppu.write_register(0xFF40, 0x80);
+
+    // the flag follows LY == LYC without the interrupt selected
+    tick_to_line(&mut ppu, &mut interrupts, 153);
+    assert_eq!(ppu.read_register(0xFF41) & 0x04, 0x04);
+    tick_to_line(&mut ppu, &mut interrupts, 0);
+    assert_eq!(ppu.read_register(0xFF41) & 0x04, 0);
+    assert_eq!(interrupts.flags & Interrupt::LCDStat.mask(), 0);
+
+    // writing LYC to the current line sets it straight away
+    ppu.write_register(0xFF45, 0);
+    ppu.tick(4, &mut interrupts);
+    assert_eq!(ppu.read_register(0xFF41) & 0x04, 0x04);
+}
+
+#[test]
+fn test_vblank_interrupts() {
+    let mut ppu = PPU::new();
+    let mut interrupts = InterruptController::new();
+    // VBlank STAT source as well as the VBlank interrupt
+    ppu.write_register(0xFF41, 0x10);
+    ppu.write_register(0xFF40, 0x80);
+
+    tick_to_line(&mut ppu, &mut interrupts, 144);
+    assert_eq!(interrupts.flags, Interrupt::VBlank.mask() | Interrupt::LCDStat.mask());
+    interrupts.acknowledge(Interrupt::VBlank);
+    interrupts.acknowledge(Interrupt::LCDStat);
+
+    // once per frame, the STAT line stays high through VBlank
+    tick_to_line(&mut ppu, &mut interrupts, 153);
+    assert_eq!(interrupts.flags, 0);
+    tick_to_line(&mut ppu, &mut interrupts, 144);
+    assert_eq!(interrupts.flags, Interrupt::VBlank.mask() | Interrupt::LCDStat.mask());
+}
+
+#[test]
+fn test_stat_blocking_lyc_into_hblank() {
+    let mut ppu = PPU::new();
+    let mut interrupts = InterruptController::new();
+    // LYC and HBlank sources
+    ppu.write_register(0xFF45, 1);
+    ppu.write_register(0xFF41, 0x48);
+    ppu.write_register(0xFF40, 0x80);
+
+    tick_dots(&mut ppu, &mut interrupts, 252);
+    assert_eq!(interrupts.flags, Interrupt::LCDStat.mask());
+    interrupts.acknowledge(Interrupt::LCDStat);
+
+    // HBlank on line 0, LY == LYC on line 1 then its HBlank keep the line high throughout
+    tick_dots(&mut ppu, &mut interrupts, 204);
+    assert_eq!(ppu.read_register(0xFF41) & 0x07, 0x06);
+    tick_dots(&mut ppu, &mut interrupts, 252);
+    assert_eq!(ppu.read_register(0xFF41) & 0x07, 0x04);
+    assert_eq!(interrupts.flags, 0);
+
+    // line 2 drops LY == LYC with no OAM scan source, so its HBlank is a new rising edge
+    tick_dots(&mut ppu, &mut interrupts, 204 + 252);
+    assert_eq!(ppu.read_register(0xFF44), 2);
+    assert_eq!(ppu.read_register(0xFF41) & 0x07, 0x00);
+    assert_eq!(interrupts.flags, Interrupt::LCDStat.mask());
+}
+
+#[test]
+fn test_background_scroll() {
+    let mut ppu = PPU::new();
+    solid_tiles(&mut ppu);
+    ppu.vram[tiles::TILE_MAP_0] = 1;
+    ppu.write_register(0xFF40, 0x91);
+    ppu.write_register(0xFF47, 0xE4);
+
+    ppu.render_scanline(0);
+    assert_eq!(pixel(&ppu, 7, 0), 3);
+    assert_eq!(pixel(&ppu, 8, 0), 0);
+
+    // scrolled left past the edge wraps back to the start of the map
+    ppu.write_register(0xFF43, 252);
+    ppu.render_scanline(0);
+    assert_eq!(pixel(&ppu, 3, 0), 0);
+    assert_eq!(pixel(&ppu, 4, 0), 3);
+    assert_eq!(pixel(&ppu, 11, 0), 3);
+    assert_eq!(pixel(&ppu, 12, 0), 0);
+
+    // palette maps color 3 to shade 1
+    ppu.write_register(0xFF47, 0x40);
+    ppu.render_scanline(0);
+    assert_eq!(pixel(&ppu, 4, 0), 1);
+}
+
+#[test]
+fn test_window_line_counter() {
+    let mut ppu = PPU::new();
+    solid_tiles(&mut ppu);
+    // window rows 0 - 7 use tile 1, rows 8 - 15 use tile 2
+    ppu.vram[tiles::TILE_MAP_1] = 1;
+    ppu.vram[tiles::TILE_MAP_1 + 32] = 2;
+    ppu.write_register(0xFF47, 0xE4);
+    ppu.write_register(0xFF4A, 0);
+    ppu.write_register(0xFF4B, 7);
+    ppu.write_register(0xFF40, 0xF1);
+
+    for line in 0..4 {
+        ppu.render_scanline(line);
+    }
+
+    // hiding the window mid-frame pauses its line counter
+    ppu.write_register(0xFF40, 0xD1);
+    for line in 4..10 {
+        ppu.render_scanline(line);
+    }
+    assert_eq!(pixel(&ppu, 0, 5), 0);
+
+    ppu.write_register(0xFF40, 0xF1);
+    for line in 10..15 {
+        ppu.render_scanline(line);
+    }
+    assert_eq!(pixel(&ppu, 0, 10), 3);
+    assert_eq!(pixel(&ppu, 0, 13), 3);
+    assert_eq!(pixel(&ppu, 0, 14), 1);
+}
+
+// run whole lines until LY reaches line
+fn tick_to_line(ppu: &mut PPU, interrupts: &mut InterruptController, line: u8) {
+    while ppu.read_register(0xFF44) != line {
+        tick_dots(ppu, interrupts, DOTS_PER_LINE as u32);
+    }
+}
+
+#[test]
+fn test_window_moved_mid_frame() {
+    let mut ppu = PPU::new();
+    let mut interrupts = InterruptController::new();
+    solid_tiles(&mut ppu);
+    // background is tile 2, the window tile 1
+    ppu.vram[tiles::TILE_MAP_0..tiles::TILE_MAP_0 + 0x400].fill(2);
+    ppu.vram[tiles::TILE_MAP_1..tiles::TILE_MAP_1 + 0x400].fill(1);
+    ppu.write_register(0xFF47, 0xE4);
+    ppu.write_register(0xFF4A, 0);
+    ppu.write_register(0xFF4B, 7);
+    ppu.write_register(0xFF40, 0xF1);
+
+    // a status bar style split, the window slides right from line 72
+    tick_to_line(&mut ppu, &mut interrupts, 72);
+    ppu.write_register(0xFF4B, 87);
+    // and is pushed off screen from line 100
+    tick_to_line(&mut ppu, &mut interrupts, 100);
+    ppu.write_register(0xFF4B, 167);
+    tick_to_line(&mut ppu, &mut interrupts, 144);
+
+    assert_eq!(pixel(&ppu, 0, 0), 3);
+    assert_eq!(pixel(&ppu, 0, 71), 3);
+    assert_eq!(pixel(&ppu, 0, 72), 1);
+    assert_eq!(pixel(&ppu, 79, 72), 1);
+    assert_eq!(pixel(&ppu, 80, 72), 3);
+    assert_eq!(pixel(&ppu, 80, 99), 3);
+    assert_eq!(pixel(&ppu, 80, 100), 1);
+    assert_eq!(pixel(&ppu, 159, 143), 1);
+}
+
+#[test]
+fn test_window_y_set_mid_frame() {
+    let mut ppu = PPU::new();
+    let mut interrupts = InterruptController::new();
+    solid_tiles(&mut ppu);
+    // window rows 0 - 7 use tile 1, rows 8 - 15 use tile 2
+    ppu.vram[tiles::TILE_MAP_1] = 1;
+    ppu.vram[tiles::TILE_MAP_1 + 32] = 2;
+    ppu.write_register(0xFF47, 0xE4);
+    ppu.write_register(0xFF4A, 200);
+    ppu.write_register(0xFF4B, 7);
+    ppu.write_register(0xFF40, 0xF1);
+
+    // moving WY onto a later line shows the window from there, starting at its first row
+    tick_to_line(&mut ppu, &mut interrupts, 50);
+    ppu.write_register(0xFF4A, 100);
+    tick_to_line(&mut ppu, &mut interrupts, 144);
+
+    assert_eq!(pixel(&ppu, 0, 99), 0);
+    assert_eq!(pixel(&ppu, 0, 100), 3);
+    assert_eq!(pixel(&ppu, 0, 107), 3);
+    assert_eq!(pixel(&ppu, 0, 108), 1);
+}
+
+#[test]
+fn test_sprites() {
+    let mut ppu = PPU::new();
+    solid_tiles(&mut ppu);
+    ppu.write_register(0xFF47, 0xE4);
+    ppu.write_register(0xFF48, 0xE4);
+    ppu.write_register(0xFF49, 0x1B);
+    ppu.write_register(0xFF40, 0x93);
+
+    // sprite 0 at screen x 4 with OBP1, sprite 1 at screen x 0 with OBP0
+    ppu.oam[0..4].copy_from_slice(&[16, 12, 2, 0x10]);
+    ppu.oam[4..8].copy_from_slice(&[16, 8, 1, 0x00]);
+    ppu.render_scanline(0);
+    assert_eq!(pixel(&ppu, 0, 0), 3);
+    // the sprite with the smaller X is drawn on top where they overlap
+    assert_eq!(pixel(&ppu, 7, 0), 3);
+    // OBP1 maps color 1 to shade 2
+    assert_eq!(pixel(&ppu, 8, 0), 2);
+    assert_eq!(pixel(&ppu, 12, 0), 0);
+
+    // background colors 1 - 3 cover sprites with the priority bit
+    ppu.vram[tiles::TILE_MAP_0] = 2;
+    ppu.oam[7] = 0x80;
+    ppu.render_scanline(0);
+    assert_eq!(pixel(&ppu, 0, 0), 1);
+    // the hidden sprite still wins over the lower priority sprite beneath it
+    assert_eq!(pixel(&ppu, 4, 0), 1);
+
+    // obj_en clear hides sprites
+    ppu.write_register(0xFF40, 0x91);
+    ppu.render_scanline(0);
+    assert_eq!(pixel(&ppu, 8, 0), 0);
+}
@@ -0,0 +1,34 @@
+// Tiles are 8x8 pixels at 2 bits per pixel, 16 bytes each
+// every row is two bytes, the first holds the low bit of each pixel's color ID
+// offsets below are relative to the start of VRAM at 0x8000
+
+pub const TILE_MAP_0: usize = 0x1800; // 0x9800
+pub const TILE_MAP_1: usize = 0x1C00; // 0x9C00
+
+const TILE_SIZE: usize = 16;
+
+// tile_sel set: 0x8000 - 0x8FFF with unsigned indices
+// tile_sel clear: 0x9000 based with signed indices, reaching down to 0x8800
+pub fn tile_data_address(tile_index: u8, tile_sel: bool) -> usize {
+    if tile_sel {
+        tile_index as usize * TILE_SIZE
+    } else {
+        (0x1000 + tile_index as i8 as isize * TILE_SIZE as isize) as usize
+    }
+}
+
+// Color ID 0 - 3 of the pixel at row, col in the tile starting at tile_address
+pub fn tile_pixel(vram: &[u8], tile_address: usize, row: u8, col: u8) -> u8 {
+    let low = vram[tile_address + row as usize * 2];
+    let high = vram[tile_address + row as usize * 2 + 1];
+    let bit = 7 - col;
+    ((high >> bit) & 0b1) << 1 | ((low >> bit) & 0b1)
+}
+
+// Tile index under pixel x, y of the 256x256 map starting at map_base
+pub fn tile_map_index(vram: &[u8], map_base: usize, x: u8, y: u8) -> u8 {
+    vram[map_base + (y as usize / 8) * 32 + x as usize / 8]
+}
+
+#[cfg(test)]
+mod test_tiles;
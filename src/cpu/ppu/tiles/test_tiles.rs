@@ -0,0 +1,48 @@
+use super::*;
+use super::super::PaletteRegister;
+
+#[test]
+fn test_tile_data_address() {
+    // unsigned indices from 0x8000
+    assert_eq!(tile_data_address(0x00, true), 0x0000);
+    assert_eq!(tile_data_address(0x80, true), 0x0800);
+    assert_eq!(tile_data_address(0xFF, true), 0x0FF0);
+
+    // signed indices around 0x9000
+    assert_eq!(tile_data_address(0x00, false), 0x1000);
+    assert_eq!(tile_data_address(0x7F, false), 0x17F0);
+    assert_eq!(tile_data_address(0x80, false), 0x0800);
+    assert_eq!(tile_data_address(0xFF, false), 0x0FF0);
+}
+
+#[test]
+fn test_tile_pixel() {
+    let mut vram = [0; 0x2000];
+    // row 1: low 0b1010_0000, high 0b1100_0000 -> IDs 3, 2, 1, 0
+    vram[0x0012] = 0b1010_0000;
+    vram[0x0013] = 0b1100_0000;
+    assert_eq!(tile_pixel(&vram, 0x0010, 1, 0), 3);
+    assert_eq!(tile_pixel(&vram, 0x0010, 1, 1), 2);
+    assert_eq!(tile_pixel(&vram, 0x0010, 1, 2), 1);
+    assert_eq!(tile_pixel(&vram, 0x0010, 1, 3), 0);
+    assert_eq!(tile_pixel(&vram, 0x0010, 0, 0), 0);
+}
+
+#[test]
+fn test_tile_map_index() {
+    let mut vram = [0; 0x2000];
+    vram[TILE_MAP_1 + 2 * 32 + 3] = 0x42;
+    assert_eq!(tile_map_index(&vram, TILE_MAP_1, 3 * 8 + 7, 2 * 8), 0x42);
+    assert_eq!(tile_map_index(&vram, TILE_MAP_0, 3 * 8, 2 * 8), 0x00);
+}
+
+#[test]
+fn test_palette() {
+    let bgp = PaletteRegister::from(0b1110_0100);
+    assert_eq!(bgp.shades, [0, 1, 2, 3]);
+    assert_eq!(bgp.shade(3), 3);
+    assert_eq!(u8::from(bgp), 0b1110_0100);
+
+    let inverted = PaletteRegister::from(0b0001_1011);
+    assert_eq!(inverted.shade(0), 3);
+}
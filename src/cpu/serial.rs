@@ -0,0 +1,77 @@
+use super::Interrupt;
+use super::InterruptController;
+
+// cycles per bit with the internal 8192 Hz clock
+const CYCLES_PER_BIT: u16 = 512;
+
+const START_BYTE_POSITION: u8 = 7;
+const CLOCK_BYTE_POSITION: u8 = 0;
+
+// Serial data SB 0xFF01 and control SC 0xFF02
+// Nothing is ever connected, so each transfer shifts in 0xFF. With the external clock
+// selected the transfer waits for a partner forever, just as on hardware
+pub struct Serial {
+    pub data: u8,
+    control: u8,
+    bits_left: u8,
+    timer: u16, // cycles until the next bit shifts
+    // bytes sent with the internal clock, test ROMs print through this
+    // nothing clears it here, whoever runs the emulator has to drain it
+    pub output: Vec<u8>,
+}
+
+impl Serial {
+    pub fn new() -> Serial {
+        Serial {
+            data: 0,
+            control: 0,
+            bits_left: 0,
+            timer: 0,
+            output: Vec::new(),
+        }
+    }
+
+    // unused bits of SC read back as 1
+    pub fn read_control(&self) -> u8 {
+        self.control | 0x7E
+    }
+
+    pub fn write_control(&mut self, value: u8) {
+        self.control = value & 0x81;
+        let start = (value >> START_BYTE_POSITION) & 0b1 != 0;
+        let internal_clock = (value >> CLOCK_BYTE_POSITION) & 0b1 != 0;
+        if start && internal_clock {
+            self.output.push(self.data);
+            self.bits_left = 8;
+            self.timer = CYCLES_PER_BIT;
+        } else {
+            self.bits_left = 0;
+        }
+    }
+
+    // Advance by cycles, the interrupt is requested once all 8 bits have shifted
+    pub fn tick(&mut self, cycles: u8, interrupts: &mut InterruptController) {
+        if self.bits_left == 0 {
+            return;
+        }
+
+        let mut cycles = cycles as u16;
+        while cycles > 0 && self.bits_left > 0 {
+            let step = cycles.min(self.timer);
+            cycles -= step;
+            self.timer -= step;
+            if self.timer == 0 {
+                self.timer = CYCLES_PER_BIT;
+                self.data = (self.data << 1) | 0b1;
+                self.bits_left -= 1;
+                if self.bits_left == 0 {
+                    self.control &= !(1 << START_BYTE_POSITION);
+                    interrupts.request(Interrupt::Serial);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_serial;
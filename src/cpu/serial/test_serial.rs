@@ -0,0 +1,66 @@
+use super::*;
+
+fn tick_cycles(serial: &mut Serial, interrupts: &mut InterruptController, cycles: u32) {
+    for _ in 0..cycles / 4 {
+        serial.tick(4, interrupts);
+    }
+}
+
+#[test]
+fn test_internal_clock_transfer() {
+    let mut serial = Serial::new();
+    let mut interrupts = InterruptController::new();
+    serial.data = 0x42;
+    serial.write_control(0x81);
+    assert_eq!(serial.read_control(), 0xFF);
+    assert_eq!(serial.output, vec![0x42]);
+
+    // one bit every 512 cycles, 1s shift in from the empty link port
+    tick_cycles(&mut serial, &mut interrupts, 512);
+    assert_eq!(serial.data, 0x85);
+    tick_cycles(&mut serial, &mut interrupts, 512 * 6);
+    assert_eq!(serial.read_control(), 0xFF);
+    assert_eq!(interrupts.flags, 0);
+
+    tick_cycles(&mut serial, &mut interrupts, 512);
+    assert_eq!(serial.data, 0xFF);
+    assert_eq!(serial.read_control(), 0x7F);
+    assert_eq!(interrupts.flags, Interrupt::Serial.mask());
+
+    // nothing more happens until the next transfer
+    interrupts.acknowledge(Interrupt::Serial);
+    tick_cycles(&mut serial, &mut interrupts, 512 * 8);
+    assert_eq!(interrupts.flags, 0);
+}
+
+#[test]
+fn test_external_clock_waits() {
+    let mut serial = Serial::new();
+    let mut interrupts = InterruptController::new();
+    serial.data = 0x42;
+    serial.write_control(0x80);
+    tick_cycles(&mut serial, &mut interrupts, 512 * 16);
+    assert_eq!(serial.data, 0x42);
+    assert_eq!(serial.read_control(), 0xFE);
+    assert_eq!(interrupts.flags, 0);
+    assert!(serial.output.is_empty());
+}
+
+#[test]
+fn test_output_bytes() {
+    let mut serial = Serial::new();
+    let mut interrupts = InterruptController::new();
+    for &byte in b"ok" {
+        serial.data = byte;
+        serial.write_control(0x81);
+        tick_cycles(&mut serial, &mut interrupts, 512 * 8);
+    }
+    assert_eq!(serial.output, b"ok".to_vec());
+    // clearing the start bit cancels a transfer
+    serial.write_control(0x81);
+    serial.write_control(0x01);
+    tick_cycles(&mut serial, &mut interrupts, 512 * 8);
+    assert_eq!(serial.read_control(), 0x7F);
+    assert_eq!(interrupts.flags, Interrupt::Serial.mask());
+    assert_eq!(serial.data, 0xFF);
+}
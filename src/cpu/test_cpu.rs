@@ -10,7 +10,7 @@ impl CPU {
 
 #[test]
 fn test_add() {
-    let mut cpu = CPU::new_test();
+    let mut cpu = CPU::new();
     // test add empty
     let add = cpu.add(0xFF);
     assert_eq!(add, 0xFF);
@@ -38,7 +38,7 @@ fn test_add() {
 
 #[test]
 fn test_add16() {
-    let mut cpu = CPU::new_test();
+    let mut cpu = CPU::new();
     let add = cpu.add16(0x11, 0x22);
     assert_eq!(add, 0x33);
     cpu.check_subtract_false();
@@ -46,7 +46,7 @@ fn test_add16() {
 
 #[test]
 fn test_adc() {
-    let mut cpu = CPU::new_test();
+    let mut cpu = CPU::new();
     cpu.reg.f.carry = true;
     cpu.reg.a = 0x01;
     let adc = cpu.adc(0xFD);
@@ -62,7 +62,7 @@ fn test_adc() {
 
 #[test]
 fn test_sub() {
-    let mut cpu = CPU::new_test();
+    let mut cpu = CPU::new();
     cpu.reg.a = 0x01;
     let sub = cpu.sub(0x01);
     assert_eq!(sub, 0x00);
@@ -76,7 +76,7 @@ fn test_sub() {
 
 #[test]
 fn test_sbc() {
-    let mut cpu = CPU::new_test();
+    let mut cpu = CPU::new();
     cpu.reg.f.carry = true;
     cpu.reg.a = 0x02;
     let sbc = cpu.sbc(0x01);
@@ -95,28 +95,28 @@ fn test_sbc() {
 
 #[test]
 fn test_and() {
-    let mut cpu = CPU::new_test();
+    let mut cpu = CPU::new();
     cpu.reg.a = 0xF0;
     assert_eq!(0x00, cpu.and(0x0F));
 }
 
 #[test]
 fn test_or() {
-    let mut cpu = CPU::new_test();
+    let mut cpu = CPU::new();
     cpu.reg.a = 0xF0;
     assert_eq!(0xFF, cpu.or(0x0F));
 }
 
 #[test]
 fn test_xor() {
-    let mut cpu = CPU::new_test();
+    let mut cpu = CPU::new();
     cpu.reg.a = 0xFF;
     assert_eq!(0x55, cpu.xor(0xAA));
 }
 
 #[test]
 fn test_inc() {
-    let mut cpu = CPU::new_test();
+    let mut cpu = CPU::new();
     assert_eq!(0x01, cpu.inc(0x00));
     assert_eq!(0x02, cpu.inc(0x01));
     assert_eq!(cpu.reg.f.carry, false);
@@ -126,7 +126,7 @@ fn test_inc() {
 
 #[test]
 fn test_dec() {
-    let mut cpu = CPU::new_test();
+    let mut cpu = CPU::new();
     assert_eq!(0x01, cpu.dec(0x02));
     assert_eq!(0x00, cpu.dec(0x01));
     assert_eq!(cpu.reg.f.carry, false);
@@ -136,14 +136,14 @@ fn test_dec() {
 
 #[test]
 fn test_push() {
-    let mut cpu = CPU::new_test();
+    let mut cpu = CPU::new();
     cpu.push(0x11);
     assert_eq!(cpu.bus.read_byte(cpu.sp), 0x11);
 }
 
 #[test]
 fn test_push_pop() {
-    let mut cpu = CPU::new_test();
+    let mut cpu = CPU::new();
     for i in 0..10 {
         cpu.push(i);
     }
@@ -167,7 +167,7 @@ fn test_push_pop() {
 
 #[test]
 fn test_ld() {
-    let mut cpu = CPU::new_test();
+    let mut cpu = CPU::new();
     let instruction = Instruction::LD(LoadType::Byte(LoadByteDestination::A, LoadByteSource::B));
     cpu.reg.b = 0xF1;
     cpu.is_halted = false;
@@ -182,7 +182,7 @@ fn test_ld() {
 
 #[test]
 fn test_sub_from_byte() {
-    let mut cpu = CPU::new_test();
+    let mut cpu = CPU::new();
     let instruction = Instruction::from_byte(0x90, false);
     assert!(instruction.is_some());
     cpu.reg.a = 0x9;
@@ -195,7 +195,7 @@ fn test_sub_from_byte() {
 
 #[test]
 fn test_add_from_byte() {
-    let mut cpu = CPU::new_test();
+    let mut cpu = CPU::new();
     let instruction = Instruction::from_byte(0x80, false);
     assert!(instruction.is_some());
     cpu.reg.a = 0x8;
@@ -204,4 +204,474 @@ fn test_add_from_byte() {
     cpu.is_stopped = false;
     cpu.execute(instruction.unwrap());
     assert_eq!(cpu.reg.a, 0xC);
-}
\ No newline at end of file
+}
+
+// 0xCB prefixed instructions
+
+#[test]
+fn test_rotates() {
+    let mut cpu = CPU::new();
+    assert_eq!(cpu.rlc(0x85), 0x0B);
+    assert!(cpu.reg.f.carry);
+    assert_eq!(cpu.rrc(0x01), 0x80);
+    assert!(cpu.reg.f.carry);
+
+    // rotate through carry
+    cpu.reg.f.carry = false;
+    assert_eq!(cpu.rl(0x80), 0x00);
+    assert!(cpu.reg.f.carry);
+    cpu.check_zero_reg(0x00);
+    assert_eq!(cpu.rr(0x00), 0x80);
+    assert!(!cpu.reg.f.carry);
+    cpu.check_subtract_false();
+}
+
+#[test]
+fn test_shifts() {
+    let mut cpu = CPU::new();
+    assert_eq!(cpu.sla(0x81), 0x02);
+    assert!(cpu.reg.f.carry);
+    assert_eq!(cpu.sra(0x81), 0xC0);
+    assert!(cpu.reg.f.carry);
+    assert_eq!(cpu.srl(0x81), 0x40);
+    assert!(cpu.reg.f.carry);
+    assert_eq!(cpu.swap(0xF1), 0x1F);
+    assert!(!cpu.reg.f.carry);
+    assert_eq!(cpu.swap(0x00), 0x00);
+    cpu.check_zero_reg(0x00);
+}
+
+#[test]
+fn test_prefixed_from_byte() {
+    for byte in 0..=0xFF {
+        assert!(Instruction::from_byte(byte, true).is_some());
+        assert!(get_cycle_count(byte, true) >= 8);
+    }
+}
+
+#[test]
+fn test_bit_res_set() {
+    let mut cpu = CPU::new();
+    cpu.is_halted = false;
+    cpu.is_stopped = false;
+
+    // BIT 7, H
+    cpu.reg.h = 0x7F;
+    cpu.execute(Instruction::from_byte(0x7C, true).unwrap());
+    assert!(cpu.reg.f.zero);
+    assert!(cpu.reg.f.half_carry);
+
+    // SET 7, H then BIT 7, H
+    cpu.execute(Instruction::from_byte(0xFC, true).unwrap());
+    assert_eq!(cpu.reg.h, 0xFF);
+    cpu.execute(Instruction::from_byte(0x7C, true).unwrap());
+    assert!(!cpu.reg.f.zero);
+
+    // RES 0, (HL)
+    cpu.reg.set_hl(0xC000);
+    cpu.bus.write_byte(0xC000, 0xFF);
+    cpu.execute(Instruction::from_byte(0x86, true).unwrap());
+    assert_eq!(cpu.bus.read_byte(0xC000), 0xFE);
+}
+
+#[test]
+fn test_prefixed_step() {
+    let mut cpu = CPU::new();
+    cpu.is_halted = false;
+    cpu.is_stopped = false;
+
+    // SWAP A
+    cpu.bus.cartridge.rom[0] = 0xCB;
+    cpu.bus.cartridge.rom[1] = 0x37;
+    cpu.reg.a = 0xAB;
+    let cycles = cpu.step();
+    assert_eq!(cpu.reg.a, 0xBA);
+    assert_eq!(cpu.pc, 2);
+    assert_eq!(cycles, 8);
+
+    // SRL (HL)
+    cpu.bus.cartridge.rom[2] = 0xCB;
+    cpu.bus.cartridge.rom[3] = 0x3E;
+    cpu.reg.set_hl(0xC000);
+    cpu.bus.wram[0] = 0x02;
+    let cycles = cpu.step();
+    assert_eq!(cpu.bus.wram[0], 0x01);
+    assert_eq!(cpu.pc, 4);
+    assert_eq!(cycles, 16);
+}
+
+// opcode table
+
+// opcodes with no instruction on the SM83
+const ILLEGAL_OPCODES: [u8; 11] = [0xD3, 0xDB, 0xDD, 0xE3, 0xE4, 0xEB, 0xEC, 0xED, 0xF4, 0xFC, 0xFD];
+
+#[test]
+fn test_not_prefixed_from_byte() {
+    for byte in 0..=0xFF {
+        if byte == 0xCB {
+            // prefix byte, decoded through the prefixed table
+            continue;
+        }
+        let instruction = Instruction::from_byte(byte, false);
+        assert_eq!(instruction.is_some(), !ILLEGAL_OPCODES.contains(&byte), "opcode 0x{:02X}", byte);
+        if instruction.is_some() {
+            assert!(get_cycle_count(byte, false) > 0, "opcode 0x{:02X} has no cycle count", byte);
+        }
+    }
+}
+
+#[test]
+fn test_rotate_a() {
+    let mut cpu = CPU::new();
+    cpu.is_halted = false;
+    cpu.is_stopped = false;
+
+    // RLCA never sets zero
+    cpu.reg.a = 0x80;
+    cpu.execute(Instruction::from_byte(0x07, false).unwrap());
+    assert_eq!(cpu.reg.a, 0x01);
+    assert!(cpu.reg.f.carry);
+    assert!(!cpu.reg.f.zero);
+
+    // RRA through carry
+    cpu.reg.a = 0x00;
+    cpu.execute(Instruction::from_byte(0x1F, false).unwrap());
+    assert_eq!(cpu.reg.a, 0x80);
+    assert!(!cpu.reg.f.carry);
+    assert!(!cpu.reg.f.zero);
+}
+
+#[test]
+fn test_push_pop_word() {
+    let mut cpu = CPU::new();
+    cpu.is_halted = false;
+    cpu.is_stopped = false;
+    cpu.sp = 0xFFFE;
+
+    // PUSH BC, POP DE
+    cpu.reg.set_bc(0x1234);
+    cpu.execute(Instruction::from_byte(0xC5, false).unwrap());
+    assert_eq!(cpu.bus.read_byte(0xFFFD), 0x12);
+    assert_eq!(cpu.bus.read_byte(0xFFFC), 0x34);
+    cpu.execute(Instruction::from_byte(0xD1, false).unwrap());
+    assert_eq!(cpu.reg.get_de(), 0x1234);
+    assert_eq!(cpu.sp, 0xFFFE);
+}
+
+#[test]
+fn test_jumps() {
+    let mut cpu = CPU::new();
+    cpu.is_halted = false;
+    cpu.is_stopped = false;
+
+    // JR -2 loops back onto itself
+    cpu.pc = 0x100;
+    cpu.bus.cartridge.rom[0x100] = 0x18;
+    cpu.bus.cartridge.rom[0x101] = 0xFE;
+    assert_eq!(cpu.step(), 12);
+    assert_eq!(cpu.pc, 0x100);
+
+    // JP C not taken, then taken
+    cpu.bus.cartridge.rom[0x100] = 0xDA;
+    cpu.bus.cartridge.rom[0x101] = 0x00;
+    cpu.bus.cartridge.rom[0x102] = 0x20;
+    cpu.reg.f.carry = false;
+    assert_eq!(cpu.step(), 12);
+    assert_eq!(cpu.pc, 0x103);
+    cpu.pc = 0x100;
+    cpu.reg.f.carry = true;
+    assert_eq!(cpu.step(), 16);
+    assert_eq!(cpu.pc, 0x2000);
+
+    // JP HL
+    cpu.reg.set_hl(0x1234);
+    cpu.bus.cartridge.rom[0x2000] = 0xE9;
+    assert_eq!(cpu.step(), 4);
+    assert_eq!(cpu.pc, 0x1234);
+}
+
+#[test]
+fn test_call_ret() {
+    let mut cpu = CPU::new();
+    cpu.is_halted = false;
+    cpu.is_stopped = false;
+    cpu.sp = 0xFFFE;
+
+    // CALL C, 0x2000
+    cpu.pc = 0x100;
+    cpu.bus.cartridge.rom[0x100] = 0xDC;
+    cpu.bus.cartridge.rom[0x101] = 0x00;
+    cpu.bus.cartridge.rom[0x102] = 0x20;
+    cpu.reg.f.carry = true;
+    assert_eq!(cpu.step(), 24);
+    assert_eq!(cpu.pc, 0x2000);
+
+    // RET
+    cpu.bus.cartridge.rom[0x2000] = 0xC9;
+    assert_eq!(cpu.step(), 16);
+    assert_eq!(cpu.pc, 0x103);
+
+    // RST 0x38 returns to the next instruction
+    cpu.bus.cartridge.rom[0x103] = 0xFF;
+    assert_eq!(cpu.step(), 16);
+    assert_eq!(cpu.pc, 0x38);
+    cpu.bus.cartridge.rom[0x38] = 0xC9;
+    cpu.step();
+    assert_eq!(cpu.pc, 0x104);
+}
+
+#[test]
+fn test_add_sp_imm() {
+    let mut cpu = CPU::new();
+    cpu.sp = 0xFFF8;
+    assert_eq!(cpu.add_sp_imm(0x08), 0x0000);
+    assert!(cpu.reg.f.carry);
+    assert!(cpu.reg.f.half_carry);
+    assert!(!cpu.reg.f.zero);
+    cpu.sp = 0x0010;
+    assert_eq!(cpu.add_sp_imm(0xFF), 0x000F);
+}
+
+#[test]
+fn test_illegal_opcode_locks() {
+    let mut cpu = CPU::new();
+    cpu.is_halted = false;
+    cpu.is_stopped = false;
+
+    for &opcode in ILLEGAL_OPCODES.iter() {
+        cpu.is_locked = false;
+        cpu.pc = 0x100;
+        cpu.bus.cartridge.rom[0x100] = opcode;
+        assert_eq!(cpu.step(), 4);
+        assert!(cpu.is_locked);
+        assert_eq!(cpu.pc, 0x100);
+    }
+
+    // locked CPU ignores everything after
+    cpu.bus.cartridge.rom[0x100] = 0x3C;
+    cpu.reg.a = 0;
+    cpu.step();
+    assert_eq!(cpu.reg.a, 0);
+    assert_eq!(cpu.pc, 0x100);
+}
+
+// interrupts
+
+#[test]
+fn test_interrupt_registers() {
+    let mut cpu = CPU::new();
+    cpu.bus.write_byte(0xFFFF, 0x1F);
+    cpu.bus.write_byte(0xFF0F, 0xFF);
+    assert_eq!(cpu.bus.read_byte(0xFFFF), 0x1F);
+    assert_eq!(cpu.bus.read_byte(0xFF0F), 0xFF);
+    assert_eq!(cpu.bus.interrupts.flags, 0x1F);
+
+    cpu.bus.write_byte(0xFF0F, 0x00);
+    cpu.bus.interrupts.request(Interrupt::Timer);
+    assert_eq!(cpu.bus.read_byte(0xFF0F), 0xE4);
+}
+
+#[test]
+fn test_interrupt_dispatch_priority() {
+    let mut cpu = CPU::new();
+    cpu.is_halted = false;
+    cpu.is_stopped = false;
+    cpu.ime = true;
+    cpu.sp = 0xFFFE;
+    cpu.pc = 0x1234;
+    cpu.bus.interrupts.enable = 0x1F;
+    cpu.bus.interrupts.request(Interrupt::Joypad);
+    cpu.bus.interrupts.request(Interrupt::Timer);
+
+    assert_eq!(cpu.step(), 20);
+    assert_eq!(cpu.pc, 0x50);
+    assert!(!cpu.ime);
+    assert_eq!(cpu.bus.interrupts.flags, Interrupt::Joypad.mask());
+
+    // RETI returns and re-enables interrupts immediately
+    cpu.bus.cartridge.rom[0x50] = 0xD9;
+    cpu.step();
+    assert_eq!(cpu.pc, 0x1234);
+    assert!(cpu.ime);
+    assert_eq!(cpu.step(), 20);
+    assert_eq!(cpu.pc, 0x60);
+}
+
+#[test]
+fn test_ei_delay() {
+    let mut cpu = CPU::new();
+    cpu.is_halted = false;
+    cpu.is_stopped = false;
+    cpu.sp = 0xFFFE;
+    cpu.bus.interrupts.enable = Interrupt::VBlank.mask();
+    cpu.bus.interrupts.request(Interrupt::VBlank);
+
+    // EI, NOP
+    cpu.bus.cartridge.rom[0] = 0xFB;
+    cpu.bus.cartridge.rom[1] = 0x00;
+    cpu.step();
+    assert!(!cpu.ime);
+    cpu.step();
+    assert!(cpu.ime);
+    assert_eq!(cpu.pc, 2);
+    assert_eq!(cpu.step(), 20);
+    assert_eq!(cpu.pc, 0x40);
+
+    // EI, DI never enables
+    cpu.pc = 0;
+    cpu.bus.cartridge.rom[1] = 0xF3;
+    cpu.step();
+    cpu.step();
+    assert!(!cpu.ime);
+}
+
+#[test]
+fn test_halt_wakes_on_interrupt() {
+    let mut cpu = CPU::new();
+    cpu.is_halted = false;
+    cpu.is_stopped = false;
+    cpu.bus.interrupts.enable = Interrupt::Timer.mask();
+
+    // HALT with IME off resumes without servicing the interrupt
+    cpu.bus.cartridge.rom[0] = 0x76;
+    cpu.step();
+    assert!(cpu.is_halted);
+    assert_eq!(cpu.step(), 4);
+    assert_eq!(cpu.pc, 1);
+
+    cpu.bus.interrupts.request(Interrupt::Timer);
+    cpu.step();
+    assert!(!cpu.is_halted);
+    assert_eq!(cpu.pc, 2);
+    assert_eq!(cpu.bus.interrupts.flags, Interrupt::Timer.mask());
+}
+
+#[test]
+fn test_halt_bug() {
+    let mut cpu = CPU::new();
+    cpu.is_halted = false;
+    cpu.is_stopped = false;
+    cpu.bus.interrupts.enable = Interrupt::Timer.mask();
+    cpu.bus.interrupts.request(Interrupt::Timer);
+
+    // HALT, INC A: INC A runs twice
+    cpu.bus.cartridge.rom[0] = 0x76;
+    cpu.bus.cartridge.rom[1] = 0x3C;
+    cpu.bus.cartridge.rom[2] = 0x00;
+    cpu.step();
+    assert!(!cpu.is_halted);
+    cpu.step();
+    assert_eq!(cpu.pc, 1);
+    cpu.step();
+    assert_eq!(cpu.pc, 2);
+    assert_eq!(cpu.reg.a, 2);
+}
+
+#[test]
+fn test_halt_bug_after_ei() {
+    let mut cpu = CPU::new();
+    cpu.is_halted = false;
+    cpu.is_stopped = false;
+    cpu.sp = 0xFFFE;
+    cpu.bus.interrupts.enable = Interrupt::Timer.mask();
+    cpu.bus.interrupts.request(Interrupt::Timer);
+
+    // EI, HALT with a JP 0x0200 at the timer vector
+    cpu.bus.cartridge.rom[0] = 0xFB;
+    cpu.bus.cartridge.rom[1] = 0x76;
+    cpu.bus.cartridge.rom[0x50] = 0xC3;
+    cpu.bus.cartridge.rom[0x51] = 0x00;
+    cpu.bus.cartridge.rom[0x52] = 0x02;
+    cpu.step();
+    cpu.step();
+    assert!(!cpu.is_halted);
+
+    // the interrupt returns to the HALT and the handler runs untouched
+    assert_eq!(cpu.step(), 20);
+    assert_eq!(cpu.pc, 0x50);
+    assert!(!cpu.halt_bug);
+    assert_eq!(cpu.bus.read_byte(0xFFFC), 0x01);
+    assert_eq!(cpu.bus.read_byte(0xFFFD), 0x00);
+    cpu.step();
+    assert_eq!(cpu.pc, 0x200);
+}
+
+#[test]
+fn test_step_clocks_timer() {
+    let mut cpu = CPU::new();
+    cpu.is_halted = false;
+    cpu.is_stopped = false;
+    cpu.bus.interrupts.enable = Interrupt::Timer.mask();
+
+    // 64 NOPs at 16 cycles per TIMA increment
+    cpu.bus.write_byte(0xFF07, 0x05);
+    for _ in 0..64 {
+        cpu.step();
+    }
+    assert_eq!(cpu.bus.read_byte(0xFF05), 16);
+    assert_eq!(cpu.bus.read_byte(0xFF04), 1);
+}
+
+#[test]
+fn test_start_post_boot() {
+    let mut cpu = CPU::new();
+    cpu.bus.cartridge.header.header_checksum = 0x3C;
+    cpu.start_post_boot();
+    assert_eq!(cpu.reg.get_af(), 0x01B0);
+    assert_eq!(cpu.reg.get_bc(), 0x0013);
+    assert_eq!(cpu.reg.get_de(), 0x00D8);
+    assert_eq!(cpu.reg.get_hl(), 0x014D);
+    assert_eq!(cpu.sp, 0xFFFE);
+    assert_eq!(cpu.pc, 0x0100);
+    assert!(!cpu.is_halted);
+
+    // a zero header checksum clears half carry and carry
+    let mut cpu = CPU::new();
+    cpu.start_post_boot();
+    assert_eq!(cpu.reg.get_af(), 0x0180);
+}
+
+#[test]
+fn test_start_boot_rom() {
+    let mut cpu = CPU::new();
+    let mut boot_rom = [0; 0x100];
+    // LD A, 0x01; LDH (0x50), A
+    boot_rom[0x00..0x04].copy_from_slice(&[0x3E, 0x01, 0xE0, 0x50]);
+    cpu.bus.cartridge.rom[0x0000] = 0x76;
+    cpu.start_boot_rom(boot_rom);
+    assert_eq!(cpu.pc, 0x0000);
+    assert_eq!(cpu.bus.read_byte(0x0000), 0x3E);
+
+    cpu.step();
+    cpu.step();
+    assert_eq!(cpu.pc, 0x0004);
+    assert_eq!(cpu.bus.read_byte(0x0000), 0x76);
+}
+
+#[test]
+fn test_stop_wakes_on_button_press() {
+    let mut cpu = CPU::new();
+    cpu.is_halted = false;
+    cpu.is_stopped = false;
+    cpu.bus.timer.set_divider(0x1234);
+
+    // STOP, INC A with the buttons selected
+    cpu.bus.write_byte(0xFF00, 0x10);
+    cpu.bus.cartridge.rom[0] = 0x10;
+    cpu.bus.cartridge.rom[1] = 0x3C;
+    cpu.step();
+    assert!(cpu.is_stopped);
+    assert_eq!(cpu.pc, 1);
+    for _ in 0..100 {
+        assert_eq!(cpu.step(), 4);
+    }
+    assert_eq!(cpu.pc, 1);
+
+    let buttons = joypad::Buttons { start: true, ..joypad::Buttons::default() };
+    cpu.bus.joypad.set_buttons(buttons, &mut cpu.bus.interrupts);
+    cpu.step();
+    assert!(!cpu.is_stopped);
+    assert_eq!(cpu.pc, 2);
+    assert_eq!(cpu.reg.a, 1);
+    assert_eq!(cpu.bus.read_byte(0xFF04), 0);
+}
@@ -0,0 +1,100 @@
+use super::Interrupt;
+use super::InterruptController;
+
+// Timer registers DIV 0xFF04, TIMA 0xFF05, TMA 0xFF06, TAC 0xFF07
+// TIMA counts falling edges of a divider bit selected by TAC, so writes to DIV
+// and TAC can also increment TIMA
+pub struct Timer {
+    divider: u16, // DIV is the upper byte
+    pub tima: u8,
+    pub tma: u8,
+    pub tac: u8,
+    overflow_pending: bool, // TIMA reads 0 for a cycle before TMA is reloaded
+}
+
+const TAC_ENABLE_BYTE_POSITION: u8 = 2;
+
+impl Timer {
+    pub fn new() -> Timer {
+        Timer {
+            divider: 0,
+            tima: 0,
+            tma: 0,
+            tac: 0,
+            overflow_pending: false,
+        }
+    }
+
+    // Advance the timer by a number of clock cycles
+    pub fn tick(&mut self, cycles: u8, interrupts: &mut InterruptController) {
+        // timer registers only change on machine cycle boundaries
+        for _ in 0..(cycles / 4) {
+            if self.overflow_pending {
+                self.overflow_pending = false;
+                self.tima = self.tma;
+                interrupts.request(Interrupt::Timer);
+            }
+            self.set_divider(self.divider.wrapping_add(4));
+        }
+    }
+
+    pub fn read_div(&self) -> u8 {
+        (self.divider >> 8) as u8
+    }
+
+    // any write resets the whole internal divider
+    pub fn write_div(&mut self) {
+        self.set_divider(0);
+    }
+
+    pub fn write_tima(&mut self, value: u8) {
+        // writing during the overflow cycle cancels the reload
+        self.overflow_pending = false;
+        self.tima = value;
+    }
+
+    // unused upper bits of TAC read back as 1
+    pub fn read_tac(&self) -> u8 {
+        self.tac | 0xF8
+    }
+
+    pub fn write_tac(&mut self, value: u8) {
+        let old_signal = self.timer_signal();
+        self.tac = value & 0x07;
+        if old_signal && !self.timer_signal() {
+            self.increment_tima();
+        }
+    }
+
+    // used directly to seed the divider with its post boot value
+    pub fn set_divider(&mut self, value: u16) {
+        let old_signal = self.timer_signal();
+        self.divider = value;
+        if old_signal && !self.timer_signal() {
+            self.increment_tima();
+        }
+    }
+
+    // divider bit selected by TAC, gated by the enable bit
+    fn timer_signal(&self) -> bool {
+        let bit = match self.tac & 0x03 {
+            0b00 => 9, // 4096 Hz
+            0b01 => 3, // 262144 Hz
+            0b10 => 5, // 65536 Hz
+            _    => 7, // 16384 Hz
+        };
+        let enabled = (self.tac >> TAC_ENABLE_BYTE_POSITION) & 0b1 != 0;
+        enabled && (self.divider >> bit) & 0b1 != 0
+    }
+
+    fn increment_tima(&mut self) {
+        let (value, did_overflow) = self.tima.overflowing_add(1);
+        self.tima = value;
+        if did_overflow {
+            self.overflow_pending = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_timer;
@@ -0,0 +1,89 @@
+use super::*;
+
+fn tick_cycles(timer: &mut Timer, interrupts: &mut InterruptController, cycles: u32) {
+    for _ in 0..(cycles / 4) {
+        timer.tick(4, interrupts);
+    }
+}
+
+#[test]
+fn test_div() {
+    let mut timer = Timer::new();
+    let mut interrupts = InterruptController::new();
+    tick_cycles(&mut timer, &mut interrupts, 255);
+    assert_eq!(timer.read_div(), 0);
+    tick_cycles(&mut timer, &mut interrupts, 4);
+    assert_eq!(timer.read_div(), 1);
+    tick_cycles(&mut timer, &mut interrupts, 256 * 10);
+    assert_eq!(timer.read_div(), 11);
+    timer.write_div();
+    assert_eq!(timer.read_div(), 0);
+}
+
+#[test]
+fn test_tima_rates() {
+    for (tac, period) in [(0x04, 1024), (0x05, 16), (0x06, 64), (0x07, 256)] {
+        let mut timer = Timer::new();
+        let mut interrupts = InterruptController::new();
+        timer.write_tac(tac);
+        tick_cycles(&mut timer, &mut interrupts, period * 3);
+        assert_eq!(timer.tima, 3, "TAC {:02X}", tac);
+    }
+
+    // disabled timer doesn't count
+    let mut timer = Timer::new();
+    let mut interrupts = InterruptController::new();
+    timer.write_tac(0x01);
+    tick_cycles(&mut timer, &mut interrupts, 1024);
+    assert_eq!(timer.tima, 0);
+    assert_eq!(timer.read_tac(), 0xF9);
+}
+
+#[test]
+fn test_tima_overflow_reload() {
+    let mut timer = Timer::new();
+    let mut interrupts = InterruptController::new();
+    timer.tma = 0xAB;
+    timer.tima = 0xFF;
+    timer.write_tac(0x05);
+
+    // overflow leaves TIMA at 0 for one machine cycle
+    tick_cycles(&mut timer, &mut interrupts, 16);
+    assert_eq!(timer.tima, 0x00);
+    assert_eq!(interrupts.flags, 0);
+    tick_cycles(&mut timer, &mut interrupts, 4);
+    assert_eq!(timer.tima, 0xAB);
+    assert_eq!(interrupts.flags, Interrupt::Timer.mask());
+}
+
+#[test]
+fn test_tima_write_cancels_reload() {
+    let mut timer = Timer::new();
+    let mut interrupts = InterruptController::new();
+    timer.tma = 0xAB;
+    timer.tima = 0xFF;
+    timer.write_tac(0x05);
+    tick_cycles(&mut timer, &mut interrupts, 16);
+    timer.write_tima(0x10);
+    tick_cycles(&mut timer, &mut interrupts, 4);
+    assert_eq!(timer.tima, 0x10);
+    assert_eq!(interrupts.flags, 0);
+}
+
+#[test]
+fn test_falling_edge_glitches() {
+    let mut timer = Timer::new();
+    let mut interrupts = InterruptController::new();
+    timer.write_tac(0x05);
+
+    // bit 3 of the divider is set, resetting DIV makes it fall
+    tick_cycles(&mut timer, &mut interrupts, 8);
+    assert_eq!(timer.tima, 0);
+    timer.write_div();
+    assert_eq!(timer.tima, 1);
+
+    // disabling the timer while the selected bit is set also counts
+    tick_cycles(&mut timer, &mut interrupts, 8);
+    timer.write_tac(0x01);
+    assert_eq!(timer.tima, 2);
+}
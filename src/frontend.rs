@@ -0,0 +1,122 @@
+use futures::executor;
+
+use winit::event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent};
+use winit::event_loop::EventLoop;
+use winit::window::{Window, WindowBuilder};
+use winit::dpi::LogicalSize;
+
+use pixels::{Error, Pixels, SurfaceTexture};
+
+use crate::cpu::ppu::{SCREEN_WIDTH, SCREEN_HEIGHT};
+use crate::Buttons;
+
+// RGBA for shades 0 - 3, lightest to darkest
+const SHADE_COLORS: [[u8; 4]; 4] = [
+    [0xE0, 0xF8, 0xD0, 0xFF],
+    [0x88, 0xC0, 0x70, 0xFF],
+    [0x34, 0x68, 0x56, 0xFF],
+    [0x08, 0x18, 0x20, 0xFF],
+];
+
+// winit window with a pixels surface that presents the PPU framebuffer
+pub struct Frontend {
+    buttons: Buttons, // held keys mapped to Game Boy buttons
+    pixels: Pixels,
+    window: Window,
+}
+
+impl Frontend {
+    pub fn new(event_loop: &EventLoop<()>) -> Frontend {
+        let window = WindowBuilder::new()
+            .with_title("rusty-gb")
+            .with_inner_size(LogicalSize::new(1000.0, 1000.0))
+            .build(event_loop)
+            .unwrap();
+
+        let window_size = window.inner_size();
+        let surface_texture = SurfaceTexture::new(window_size.width, window_size.height, &window);
+
+        // append window to web canvas
+        #[cfg(target_arch = "wasm32")]
+            Frontend::append_window_to_web_canvas(&window);
+
+        let pixels = executor::block_on(
+            Pixels::new_async(SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32, surface_texture))
+            .unwrap();
+
+        Frontend {
+            buttons: Buttons::default(),
+            pixels,
+            window,
+        }
+    }
+
+    // Draw a framebuffer of shades 0 - 3
+    pub fn render(&mut self, framebuffer: &[u8]) -> Result<(), Error> {
+        let frame: &mut [u8] = self.pixels.get_frame_mut();
+        for (rgba, &shade) in frame.chunks_exact_mut(4).zip(framebuffer.iter()) {
+            rgba.copy_from_slice(&SHADE_COLORS[shade as usize]);
+        }
+        self.pixels.render()
+    }
+
+    // Track key presses and releases, returns true when the held buttons changed
+    pub fn handle_input(&mut self, event: &Event<()>) -> bool {
+        if let Event::WindowEvent { event: WindowEvent::KeyboardInput {
+            input: KeyboardInput { virtual_keycode: Some(key), state, .. }, ..
+        }, .. } = event {
+            if let Some(button) = Frontend::button_for_key(&mut self.buttons, *key) {
+                let pressed = *state == ElementState::Pressed;
+                let changed = *button != pressed;
+                *button = pressed;
+                return changed;
+            }
+        }
+        false
+    }
+
+    pub fn buttons(&self) -> Buttons {
+        self.buttons
+    }
+
+    // arrows for the d-pad, Z and X for B and A, Enter for start and Backspace for select
+    fn button_for_key(buttons: &mut Buttons, key: VirtualKeyCode) -> Option<&mut bool> {
+        match key {
+            VirtualKeyCode::Right  => Some(&mut buttons.right),
+            VirtualKeyCode::Left   => Some(&mut buttons.left),
+            VirtualKeyCode::Up     => Some(&mut buttons.up),
+            VirtualKeyCode::Down   => Some(&mut buttons.down),
+            VirtualKeyCode::X      => Some(&mut buttons.a),
+            VirtualKeyCode::Z      => Some(&mut buttons.b),
+            VirtualKeyCode::Back   => Some(&mut buttons.select),
+            VirtualKeyCode::Return => Some(&mut buttons.start),
+            _ => None,
+        }
+    }
+
+    pub fn request_refresh(&mut self) {
+        self.window.request_redraw();
+    }
+
+    pub fn set_title(&self, title: &str) {
+        self.window.set_title(title);
+    }
+
+    #[cfg(target_arch="wasm32")]
+    fn append_window_to_web_canvas(window: &Window) {
+        // set window size manually (winit prevents sizing with CSS)
+        // use winit::dpi::PhysicalSize;
+        // window.set_inner_size(PhysicalSize::new(1000, 1000));
+
+        use winit::platform::web::WindowExtWebSys;
+        web_sys::window()
+            .and_then(|win| win.document())
+            .and_then(|doc| {
+                let dst = doc.get_element_by_id("rusty_gb_body")?;
+                let canvas = web_sys::Element::from(window.canvas());
+                dst.append_child(&canvas).ok()?;
+                Some(())
+            })
+            .expect("Unable to append to canvas")
+    }
+}
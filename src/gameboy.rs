@@ -0,0 +1,143 @@
+use crate::cpu::CPU;
+use crate::cpu::cartridge::{Cartridge, CartridgeError};
+use crate::cpu::joypad::Buttons;
+use crate::cpu::apu::DEFAULT_SAMPLE_RATE;
+
+// A whole DMG, the CPU drives the memory bus which owns the cartridge, PPU, APU, timer, joypad and serial
+pub struct GameBoy {
+    cpu: CPU,
+    boot_rom: Option<[u8; 0x100]>, // run on the next load instead of starting post boot
+    sample_rate: u32,
+    recording: bool,
+    channel_capture: bool,
+}
+
+impl GameBoy {
+    pub fn new() -> GameBoy {
+        GameBoy {
+            cpu: CPU::new(),
+            boot_rom: None,
+            sample_rate: DEFAULT_SAMPLE_RATE,
+            recording: false,
+            channel_capture: false,
+        }
+    }
+
+    pub fn set_boot_rom(&mut self, boot_rom: Option<[u8; 0x100]>) {
+        self.boot_rom = boot_rom;
+    }
+
+    // Parse and insert a ROM image, then reset
+    pub fn load_rom(&mut self, bytes: &[u8]) -> Result<(), CartridgeError> {
+        let cartridge = Cartridge::from_bytes(bytes)?;
+        self.load_cartridge(cartridge);
+        Ok(())
+    }
+
+    // Insert a cartridge and reset, starting from the boot ROM if one is set
+    pub fn load_cartridge(&mut self, cartridge: Cartridge) {
+        self.cpu = CPU::new();
+        self.cpu.bus.cartridge = cartridge;
+        self.cpu.bus.apu.set_sample_rate(self.sample_rate);
+        self.cpu.bus.apu.set_recording(self.recording);
+        self.cpu.bus.apu.set_channel_capture(self.channel_capture);
+        match self.boot_rom {
+            Some(boot_rom) => self.cpu.start_boot_rom(boot_rom),
+            None => self.cpu.start_post_boot(),
+        }
+    }
+
+    // Run until the PPU completes a frame
+    pub fn run_frame(&mut self) {
+        self.cpu.frame_step();
+    }
+
+    // Run one instruction or interrupt dispatch, returns the cycles taken
+    pub fn step_instruction(&mut self) -> u8 {
+        self.cpu.step()
+    }
+
+    // 160x144 shades 0 - 3, lightest to darkest
+    pub fn framebuffer(&self) -> &[u8] {
+        &self.cpu.bus.ppu.framebuffer
+    }
+
+    // Interleaved stereo samples at the APU sample rate produced since the last call
+    pub fn audio_samples(&mut self) -> Vec<f32> {
+        std::mem::take(&mut self.cpu.bus.apu.samples)
+    }
+
+    // Also keep the mix at exactly the sample rate, untouched by set_audio_rate_adjust, kept across loads
+    pub fn set_recording(&mut self, recording: bool) {
+        self.recording = recording;
+        self.cpu.bus.apu.set_recording(recording);
+    }
+
+    // Like audio_samples without the rate adjustment, empty unless recording is set
+    pub fn recorded_samples(&mut self) -> Vec<f32> {
+        std::mem::take(&mut self.cpu.bus.apu.record_samples)
+    }
+
+    // Also keep each channel's output on its own at the sample rate, kept across loads
+    pub fn set_channel_capture(&mut self, capture: bool) {
+        self.channel_capture = capture;
+        self.cpu.bus.apu.set_channel_capture(capture);
+    }
+
+    // Like recorded_samples for channels 1 - 4 alone, empty unless capture is set
+    pub fn channel_audio_samples(&mut self) -> [Vec<f32>; 4] {
+        std::mem::take(&mut self.cpu.bus.apu.channel_samples)
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.cpu.bus.apu.sample_rate()
+    }
+
+    // Output rate for audio_samples, kept across loads
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.sample_rate = sample_rate;
+        self.cpu.bus.apu.set_sample_rate(sample_rate);
+    }
+
+    // Scale the number of samples produced, see audio::RateControl
+    pub fn set_audio_rate_adjust(&mut self, adjust: f64) {
+        self.cpu.bus.apu.set_rate_adjust(adjust);
+    }
+
+    // Bytes sent over the link port since the last call, test ROMs print their results here
+    // They're kept until taken, so call this every frame
+    pub fn serial_output(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.cpu.bus.serial.output)
+    }
+
+    pub fn set_buttons(&mut self, buttons: Buttons) {
+        let bus = &mut self.cpu.bus;
+        bus.joypad.set_buttons(buttons, &mut bus.interrupts);
+    }
+
+    pub fn cartridge(&self) -> &Cartridge {
+        &self.cpu.bus.cartridge
+    }
+
+    pub fn cartridge_mut(&mut self) -> &mut Cartridge {
+        &mut self.cpu.bus.cartridge
+    }
+
+    pub fn pc(&self) -> u16 {
+        self.cpu.pc
+    }
+
+    // an illegal opcode hangs the CPU until the next load
+    pub fn is_locked(&self) -> bool {
+        self.cpu.is_locked
+    }
+}
+
+impl Default for GameBoy {
+    fn default() -> Self {
+        GameBoy::new()
+    }
+}
+
+#[cfg(test)]
+mod test_gameboy;
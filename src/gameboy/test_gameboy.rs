@@ -0,0 +1,108 @@
+use super::*;
+use crate::cpu::cartridge::rom_with_program;
+use crate::cpu::interrupts::Interrupt;
+use crate::cpu::ppu::{DOTS_PER_FRAME, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+// 32 KiB ROM only cartridge that loops forever at the entry point
+fn looping_rom() -> Vec<u8> {
+    // JR -2
+    rom_with_program(&[0x18, 0xFE])
+}
+
+#[test]
+fn test_load_rom() {
+    let mut gameboy = GameBoy::new();
+    gameboy.load_rom(&looping_rom()).unwrap();
+    assert_eq!(gameboy.pc(), 0x100);
+    assert!(!gameboy.is_locked());
+
+    assert_eq!(gameboy.step_instruction(), 12);
+    assert_eq!(gameboy.pc(), 0x100);
+
+    assert!(gameboy.load_rom(&[0; 0x100]).is_err());
+}
+
+#[test]
+fn test_load_with_boot_rom() {
+    let mut gameboy = GameBoy::new();
+    gameboy.set_boot_rom(Some([0; 0x100]));
+    gameboy.load_rom(&looping_rom()).unwrap();
+    assert_eq!(gameboy.pc(), 0x000);
+}
+
+#[test]
+fn test_run_frame() {
+    let mut gameboy = GameBoy::new();
+    gameboy.load_rom(&looping_rom()).unwrap();
+    gameboy.run_frame();
+    assert_eq!(gameboy.framebuffer().len(), SCREEN_WIDTH * SCREEN_HEIGHT);
+
+    // post boot LCDC has the LCD on, so every frame ends entering VBlank
+    assert_eq!(gameboy.cpu.bus.read_byte(0xFF44), 144);
+    assert_eq!(gameboy.cpu.bus.read_byte(0xFF41) & 0b11, 1);
+
+    // and the next one is a whole frame of cycles later, give or take the last JR
+    let mut cycles = 0;
+    while !gameboy.cpu.bus.ppu.frame_complete {
+        cycles += gameboy.step_instruction() as u64;
+    }
+    assert!((DOTS_PER_FRAME..DOTS_PER_FRAME + 12).contains(&cycles));
+    assert_eq!(gameboy.cpu.bus.read_byte(0xFF44), 144);
+}
+
+#[test]
+fn test_set_buttons() {
+    let mut gameboy = GameBoy::new();
+    let buttons = Buttons { start: true, ..Buttons::default() };
+    gameboy.set_buttons(buttons);
+    assert_eq!(gameboy.cpu.bus.joypad.buttons, buttons);
+}
+
+#[test]
+fn test_sample_rate() {
+    let mut gameboy = GameBoy::new();
+    gameboy.set_sample_rate(48000);
+    gameboy.load_rom(&looping_rom()).unwrap();
+    assert_eq!(gameboy.sample_rate(), 48000);
+
+    // the first frame is cut short by starting post boot
+    gameboy.run_frame();
+    gameboy.audio_samples();
+    gameboy.run_frame();
+    let samples = gameboy.audio_samples();
+    // 70224 cycles at 48 kHz is 803.6 samples, interleaved stereo
+    assert!(samples.len() == 803 * 2 || samples.len() == 804 * 2);
+    assert!(gameboy.audio_samples().is_empty());
+}
+
+#[test]
+fn test_channel_capture() {
+    let mut gameboy = GameBoy::new();
+    gameboy.load_rom(&looping_rom()).unwrap();
+    gameboy.run_frame();
+    assert!(gameboy.channel_audio_samples().iter().all(Vec::is_empty));
+
+    // capture is kept across loads
+    gameboy.set_channel_capture(true);
+    gameboy.load_rom(&looping_rom()).unwrap();
+    gameboy.run_frame();
+    let samples = gameboy.audio_samples();
+    let channels = gameboy.channel_audio_samples();
+    assert!(!samples.is_empty());
+    assert!(channels.iter().all(|channel| channel.len() == samples.len()));
+}
+
+#[test]
+fn test_serial_output() {
+    // LD A, 'A'; LDH (SB), A; LD A, 0x81; LDH (SC), A; JR -2
+    let mut gameboy = GameBoy::new();
+    gameboy.load_rom(&rom_with_program(&[0x3E, 0x41, 0xE0, 0x01, 0x3E, 0x81, 0xE0, 0x02, 0x18, 0xFE])).unwrap();
+    gameboy.run_frame();
+    assert_eq!(gameboy.serial_output(), b"A".to_vec());
+    assert!(gameboy.serial_output().is_empty());
+
+    // the transfer finished with nothing connected
+    assert_eq!(gameboy.cpu.bus.read_byte(0xFF01), 0xFF);
+    assert_eq!(gameboy.cpu.bus.read_byte(0xFF02), 0x7F);
+    assert_ne!(gameboy.cpu.bus.interrupts.flags & Interrupt::Serial.mask(), 0);
+}
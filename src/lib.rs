@@ -1,18 +1,28 @@
 // standard
-use std::{thread, time};
+use std::path::PathBuf;
 
 #[cfg(target_arch="wasm32")]
 use wasm_bindgen::prelude::*;
 
 use winit::event_loop::{ControlFlow, EventLoop};
-use winit::event::{Event, VirtualKeyCode};
+use winit::event::{Event, WindowEvent};
 
 mod cpu;
-use cpu::CPU;
-use cpu::MemoryBus;
-use cpu::Registers;
-use cpu::cpu_registers::FlagsRegister;
-use cpu::PPU;
+pub use cpu::cartridge::{Cartridge, CartridgeError};
+pub use cpu::joypad::Buttons;
+use cpu::cartridge::real_time_clock::unix_time;
+
+pub mod gameboy;
+pub use gameboy::GameBoy;
+
+pub mod audio;
+use audio::{AudioSink, NullSink, RateControl, WavRecorder};
+
+mod frontend;
+use frontend::Frontend;
+
+// seconds between saves while battery backed RAM keeps changing
+const SAVE_INTERVAL: u64 = 5;
 
 #[allow(dead_code)]
 #[cfg_attr(target_arch="wasm32", wasm_bindgen(start))]
@@ -23,37 +33,176 @@ pub fn run() {
     let event_loop = EventLoop::new();
 
     // initialize rusty-gb objects
-    let mut cpu = CPU::new(&event_loop);
-    cpu.bus.memory[0] = 0x00;
+    let mut gameboy = GameBoy::new();
+    let mut frontend = Frontend::new(&event_loop);
 
-    // TODO: load something into memory
-    //       maybe based on a param?
+    #[cfg_attr(target_arch = "wasm32", allow(unused_mut))]
+    let mut audio_enabled = true;
 
-    cpu.step();
-    assert!(cpu.is_halted);
+    // mixed audio is recorded here, with each channel alongside when record_channels is set
+    #[cfg_attr(target_arch = "wasm32", allow(unused_mut))]
+    let mut record_path: Option<PathBuf> = None;
+    #[cfg_attr(target_arch = "wasm32", allow(unused_mut))]
+    let mut record_channels = false;
 
+    // battery backed RAM is saved here, None when there's nothing to save
+    #[cfg_attr(target_arch = "wasm32", allow(unused_mut))]
+    let mut save_path: Option<PathBuf> = None;
 
-    event_loop.run(move |event, _, control_flow| {
-        if let Event::RedrawRequested(_) = event {
-            if cpu.ppu
-                .as_mut()
-                .expect("The PPU should have been initialized")
-                .render()
-                .is_err() {
-                    *control_flow = ControlFlow::Exit;
+    // usage: rusty-gb <rom> [--boot-rom <path>] [--no-audio] [--record <wav> [--record-channels]]
+    // there's no file system on the web
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let mut rom_path = None;
+        let mut boot_rom_path = None;
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--boot-rom" => boot_rom_path = args.next(),
+                "--no-audio" => audio_enabled = false,
+                "--record" => record_path = args.next().map(PathBuf::from),
+                "--record-channels" => record_channels = true,
+                _ => rom_path = Some(arg),
+            }
+        }
+
+        if let Some(boot_path) = boot_rom_path {
+            match std::fs::read(&boot_path).map(<[u8; 0x100]>::try_from) {
+                Ok(Ok(boot_rom)) => gameboy.set_boot_rom(Some(boot_rom)),
+                Ok(Err(_)) => {
+                    log::error!("Boot ROM {} should be exactly 256 bytes", boot_path);
                     return;
+                },
+                Err(e) => {
+                    log::error!("Couldn't load boot ROM {}: {}", boot_path, e);
+                    return;
+                },
+            }
+        }
+
+        if let Some(path) = rom_path {
+            match Cartridge::from_path(&path) {
+                Ok(mut cartridge) => {
+                    log::info!("Loaded \"{}\" from {}", cartridge.header.title, path);
+                    if cartridge.header.has_battery() {
+                        let sav = Cartridge::save_path(&path);
+                        if let Err(e) = cartridge.load_save(&sav) {
+                            log::error!("Couldn't load save {}: {}", sav.display(), e);
+                        }
+                        save_path = Some(sav);
+                    }
+                    gameboy.load_cartridge(cartridge);
+                },
+                Err(e) => {
+                    log::error!("Couldn't load {}: {}", path, e);
+                    return;
+                },
+            }
+        }
+    }
+
+    let mut sink: Box<dyn AudioSink> = if audio_enabled {
+        audio::default_sink()
+    } else {
+        Box::new(NullSink::default())
+    };
+    gameboy.set_sample_rate(sink.sample_rate());
+    let rate_control = RateControl::new(sink.sample_rate());
+
+    let mut recorder = None;
+    if let Some(path) = record_path {
+        match WavRecorder::create(&path, sink.sample_rate(), record_channels) {
+            Ok(wav) => recorder = Some(wav),
+            Err(e) => {
+                log::error!("Couldn't create {}: {}", path.display(), e);
+                return;
+            },
+        }
+        gameboy.set_recording(true);
+        gameboy.set_channel_capture(record_channels);
+    }
+
+    let mut reported_lock = false;
+    let mut last_save = unix_time();
+
+    event_loop.run(move |event, _, control_flow| {
+        if frontend.handle_input(&event) {
+            gameboy.set_buttons(frontend.buttons());
+        }
+
+        match event {
+            Event::WindowEvent { event: WindowEvent::CloseRequested, .. } => {
+                *control_flow = ControlFlow::Exit;
+                return;
+            },
+            Event::LoopDestroyed => {
+                write_save(gameboy.cartridge_mut(), &save_path);
+                if let Some(Err(e)) = recorder.take().map(WavRecorder::finalize) {
+                    log::error!("Couldn't finish recording: {}", e);
                 }
+                return;
+            },
+            _ => {},
+        }
+
+        if gameboy.is_locked() && !reported_lock {
+            // keep the window open so the diagnostic can be read
+            frontend.set_title(&format!("rusty-gb - CPU locked at PC={:04X}", gameboy.pc()));
+            reported_lock = true;
         }
 
-        // cpu.frame_step();
-        cpu.ppu
-            .as_mut()
-            .expect("The PPU should have been initialized")
-            .request_refresh();
-        // thread::sleep(time::Duration::from_micros(cpu.frame_delay));
+        if let Event::RedrawRequested(_) = event {
+            // the audio clock paces emulation, a sink that isn't played in real time leaves it to vsync
+            match sink.buffered_frames() {
+                Some(buffered) if rate_control.should_wait(buffered) => {},
+                buffered => {
+                    if let Some(buffered) = buffered {
+                        gameboy.set_audio_rate_adjust(rate_control.rate_adjust(buffered));
+                    }
+                    gameboy.run_frame();
+                    sink.queue(&gameboy.audio_samples());
+                    record(&mut recorder, &gameboy.recorded_samples(), &gameboy.channel_audio_samples());
+                    // nothing is linked, but the bytes pile up until drained
+                    let serial = gameboy.serial_output();
+                    if !serial.is_empty() {
+                        log::debug!("Serial: {}", String::from_utf8_lossy(&serial));
+                    }
+                },
+            }
+
+            if gameboy.cartridge().dirty && unix_time() >= last_save + SAVE_INTERVAL {
+                write_save(gameboy.cartridge_mut(), &save_path);
+                last_save = unix_time();
+            }
+
+            if frontend.render(gameboy.framebuffer()).is_err() {
+                *control_flow = ControlFlow::Exit;
+                return;
+            }
+        }
+
+        frontend.request_refresh();
     });
 }
 
+// stop recording on the first error rather than logging every frame
+fn record(recorder: &mut Option<WavRecorder>, samples: &[f32], channels: &[Vec<f32>; 4]) {
+    if let Some(wav) = recorder {
+        if let Err(e) = wav.write(samples, channels) {
+            log::error!("Couldn't write recording: {}", e);
+            *recorder = None;
+        }
+    }
+}
+
+fn write_save(cartridge: &mut Cartridge, save_path: &Option<PathBuf>) {
+    if let Some(path) = save_path {
+        if let Err(e) = cartridge.save(path) {
+            log::error!("Couldn't write save {}: {}", path.display(), e);
+        }
+    }
+}
+
 fn configure_logger() {
     // configure logger based on target arch
     cfg_if::cfg_if! {